@@ -1,4 +1,7 @@
 fn main() -> std::io::Result<()> {
+    if std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default() != "windows" {
+        return Ok(());
+    }
     winresource::WindowsResource::new()
         .set_icon("icon.ico")
         .set_version_info(winresource::VersionInfo::PRODUCTVERSION, 0x0001000000000000)
@@ -0,0 +1,1270 @@
+use nwd::NwgUi;
+use nwg::NativeUi;
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::winuser::{LoadIconW, SetClassLongPtrA, GCLP_HICON, GCLP_HICONSM, MAKEINTRESOURCEW, WS_EX_TRANSPARENT};
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::os::windows::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::{convert::TryFrom, sync::Arc};
+use winapi::um::winbase::CREATE_NO_WINDOW;
+
+use crate::capture::CaptureSchedule;
+use crate::clock::SystemClock;
+use crate::config::{ConfigError, GrowerConfig};
+use crate::cropper::Cropper;
+use crate::display::Frame;
+use crate::format_duration;
+use crate::geometry::{Anchor, WindowRegion};
+use crate::grower::{CurrentlySelected, Grower, Selection};
+use crate::idle::SystemInputActivity;
+use crate::image::{Image, ImageView, PixelFormat};
+use crate::input::InputMethod;
+use crate::profile::{Profile, RecoveryChoice};
+use crate::screenshot::{Rectangle, Screenshot};
+use crate::state::GrowerState;
+use crate::window::{GameWindow, SystemDesktop};
+use crate::{bench, bmp, geometry, grower, hotbar, log, richbuilder, screenshot, window};
+
+const GROWER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+/// The hotbar stacks change a lot slower than the selection
+const HOTBAR_READ_INTERVAL: Duration = Duration::from_secs(10);
+
+thread_local! {
+    /// Every bot window, kept until the app exits. Closed ones are just hidden.
+    static BOTS: RefCell<Vec<basic_app_ui::BasicAppUi>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The app exits once the last bot window is closed
+static OPEN_BOTS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Default, NwgUi)]
+pub struct BasicApp {
+    #[nwg_control(size: (275, 690), position: (100, 100), icon: None, topmost: true, title: "Gardenbot", flags: "WINDOW|VISIBLE")]
+    #[nwg_events(OnInit: [BasicApp::on_init], OnWindowClose: [BasicApp::on_close])]
+    window: nwg::Window,
+
+    #[nwg_control(flags: "VISIBLE|MULTI_LINE|DISABLED", position: (10, 10), size: (255, 65), ex_flags: WS_EX_TRANSPARENT)]
+    rich_text_box: nwg::RichLabel,
+
+    #[nwg_control(text: "Start", position: (135, 10), size: (62, 25))]
+    #[nwg_events(OnButtonClick: [BasicApp::on_startstop_btn])]
+    startstop_btn: nwg::Button,
+
+    #[nwg_control(text: "Pause", enabled: false, position: (203, 10), size: (62, 25))]
+    #[nwg_events(OnButtonClick: [BasicApp::on_pause_btn])]
+    pause_btn: nwg::Button,
+
+    #[nwg_control(position: (10, 76), size: (255, 30))]
+    select_area_bgimg: nwg::ImageFrame,
+
+    #[nwg_control(position: (10, 76), size: (0, 0))]
+    select_area_img: nwg::ImageFrame,
+
+    #[nwg_control(text: "Selected Entity Area:", position: (10, 118), size: (150, 20))]
+    select_area_label: nwg::Label,
+
+    #[nwg_control(text: "Set", position: (175, 113), size: (90, 25))]
+    #[nwg_events(OnButtonClick: [BasicApp::on_select_area_btn])]
+    select_area_btn: nwg::Button,
+
+    #[nwg_control(text: "Num rounds:", position: (10, 145), size: (150, 20))]
+    num_rounds_label: nwg::Label,
+
+    #[nwg_control(text: "0", flags: "NUMBER|VISIBLE", limit: 3, align: nwg::HTextAlign::Center, position: (176, 143), size: (88, 20))]
+    num_rounds_input: nwg::TextInput,
+
+    #[nwg_control(text: "Harvested plants:", position: (10, 170), size: (150, 20))]
+    goal_plants_label: nwg::Label,
+
+    #[nwg_control(text: "0", flags: "NUMBER|VISIBLE", limit: 4, align: nwg::HTextAlign::Center, position: (176, 168), size: (88, 20))]
+    goal_plants_input: nwg::TextInput,
+
+    #[nwg_control(text: "Run for (min):", position: (10, 195), size: (150, 20))]
+    goal_minutes_label: nwg::Label,
+
+    #[nwg_control(text: "0", flags: "NUMBER|VISIBLE", limit: 4, align: nwg::HTextAlign::Center, position: (176, 193), size: (88, 20))]
+    goal_minutes_input: nwg::TextInput,
+
+    #[nwg_control(text: "Stop at (HH:MM):", position: (10, 220), size: (150, 20))]
+    stop_at_label: nwg::Label,
+
+    #[nwg_control(text: "", limit: 5, align: nwg::HTextAlign::Center, position: (176, 218), size: (88, 20))]
+    stop_at_input: nwg::TextInput,
+
+    #[nwg_control(text: "Until out of stock:", position: (10, 245), size: (150, 20))]
+    until_out_of_stock_label: nwg::Label,
+
+    #[nwg_control(text: "", position: (176, 243), size: (88, 20))]
+    until_out_of_stock_check: nwg::CheckBox,
+
+    #[nwg_control(text: "Num growing objects:", position: (10, 270), size: (150, 20))]
+    num_growing_objects_label: nwg::Label,
+
+    #[nwg_control(text: "0", flags: "NUMBER|VISIBLE", limit: 2, align: nwg::HTextAlign::Center, position: (176, 268), size: (40, 20))]
+    num_growing_objects_input: nwg::TextInput,
+
+    #[nwg_control(text: "Count", position: (221, 266), size: (44, 24))]
+    #[nwg_events(OnButtonClick: [BasicApp::on_count_btn])]
+    count_btn: nwg::Button,
+
+    #[nwg_control(text: "Extra delay (sec):", position: (10, 296), size: (150, 20))]
+    extra_delay_sec_label: nwg::Label,
+
+    #[nwg_control(text: "0", flags: "NUMBER|VISIBLE", limit: 2, align: nwg::HTextAlign::Center, position: (176, 293), size: (88, 20))]
+    extra_delay_sec_input: nwg::TextInput,
+
+    #[nwg_control(text: "Background input:", position: (10, 321), size: (150, 20))]
+    background_input_label: nwg::Label,
+
+    #[nwg_control(text: "", position: (176, 319), size: (88, 20))]
+    background_input_check: nwg::CheckBox,
+
+    #[nwg_control(text: "Per-plot actions:", position: (10, 347), size: (150, 20))]
+    per_plot_label: nwg::Label,
+
+    #[nwg_control(text: "", position: (176, 345), size: (88, 20))]
+    per_plot_check: nwg::CheckBox,
+
+    #[nwg_control(text: "Profile:", position: (10, 373), size: (150, 20))]
+    profile_label: nwg::Label,
+
+    #[nwg_control(collection: Profile::list(), selected_index: Some(0), position: (176, 370), size: (88, 23))]
+    #[nwg_events(OnComboxBoxSelection: [BasicApp::on_profile_select])]
+    profile_combo: nwg::ComboBox<String>,
+
+    #[nwg_control(text: "Client:", position: (10, 399), size: (60, 20))]
+    client_label: nwg::Label,
+
+    #[nwg_control(position: (70, 396), size: (150, 23))]
+    client_combo: nwg::ComboBox<String>,
+
+    #[nwg_control(text: "Find", position: (225, 395), size: (40, 25))]
+    #[nwg_events(OnButtonClick: [BasicApp::on_find_clients_btn])]
+    find_clients_btn: nwg::Button,
+
+    #[nwg_control(text: "Hotbar Area:", position: (10, 424), size: (150, 20))]
+    hotbar_area_label: nwg::Label,
+
+    #[nwg_control(text: "Set", position: (175, 420), size: (90, 25))]
+    #[nwg_events(OnButtonClick: [BasicApp::on_hotbar_area_btn])]
+    hotbar_area_btn: nwg::Button,
+
+    #[nwg_control(text: "Sentinel Area:", position: (10, 449), size: (150, 20))]
+    sentinel_area_label: nwg::Label,
+
+    #[nwg_control(text: "Set", position: (175, 445), size: (90, 25))]
+    #[nwg_events(OnButtonClick: [BasicApp::on_sentinel_area_btn])]
+    sentinel_area_btn: nwg::Button,
+
+    #[nwg_control(flags: "VISIBLE|DISABLED", position: (10, 474), size: (255, 20), ex_flags: WS_EX_TRANSPARENT)]
+    hotbar_text: nwg::RichLabel,
+
+    #[nwg_control(flags: "VISIBLE|DISABLED", position: (10, 496), size: (255, 20), ex_flags: WS_EX_TRANSPARENT)]
+    progress_text: nwg::RichLabel,
+
+    #[nwg_control(list_style: nwg::ListViewStyle::Detailed, ex_flags: nwg::ListViewExFlags::GRID | nwg::ListViewExFlags::FULL_ROW_SELECT, position: (10, 520), size: (255, 130))]
+    garden_list: nwg::ListView,
+
+    #[nwg_control(text: "Open another bot", position: (10, 655), size: (255, 25))]
+    #[nwg_events(OnButtonClick: [BasicApp::on_new_bot_btn])]
+    new_bot_btn: nwg::Button,
+
+    #[nwg_control(size: (275, 135), position: (380, 100), topmost: true, title: "Gardenbot - what now?", flags: "WINDOW")]
+    recovery_window: nwg::Window,
+
+    #[nwg_control(parent: recovery_window, position: (10, 10), size: (255, 30))]
+    recovery_img: nwg::ImageFrame,
+
+    #[nwg_control(parent: recovery_window, text: "", position: (10, 45), size: (255, 40))]
+    recovery_label: nwg::Label,
+
+    #[nwg_control(parent: recovery_window, text: "Resume", position: (10, 95), size: (80, 25))]
+    #[nwg_events(OnButtonClick: [BasicApp::on_recovery_resume_btn])]
+    recovery_resume_btn: nwg::Button,
+
+    #[nwg_control(parent: recovery_window, text: "Skip plot", position: (97, 95), size: (80, 25))]
+    #[nwg_events(OnButtonClick: [BasicApp::on_recovery_skip_btn])]
+    recovery_skip_btn: nwg::Button,
+
+    #[nwg_control(parent: recovery_window, text: "Stop", position: (185, 95), size: (80, 25))]
+    #[nwg_events(OnButtonClick: [BasicApp::on_recovery_stop_btn])]
+    recovery_stop_btn: nwg::Button,
+
+    #[nwg_control(parent: window, interval: Duration::from_millis(1500))]
+    #[nwg_events(OnTimerTick: [BasicApp::on_tick_1s])]
+    timer_1s: nwg::AnimationTimer,
+
+    state: Mutex<AppState>,
+    // outside the state mutex because accessing it can block
+    cropper: Mutex<Cropper>,
+}
+
+struct AppState {
+    profile: Profile,
+    select_region: Option<WindowRegion>,
+    select_rect_bitmap: nwg::Bitmap,
+    /// last capture of the selected entity area
+    last_capture: Option<Image>,
+    recovery_bitmap: nwg::Bitmap,
+    hotbar_region: Option<WindowRegion>,
+    sentinel_region: Option<WindowRegion>,
+    /// game clients listed in the window
+    clients: Vec<GameWindow>,
+    /// last stack counts read from the hotbar, per slot. Filled in by the
+    /// thread reading them.
+    hotbar_counts: Arc<Mutex<BTreeMap<u8, usize>>>,
+    /// when the last hotbar read was started
+    hotbar_read_at: Option<Instant>,
+    hotbar_reading: Arc<AtomicBool>,
+    scanned_str: Option<String>,
+    selected: Selection,
+
+    grower: Arc<Grower>,
+    capture: CaptureSchedule,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self {
+            profile: Profile::default(),
+            select_region: None,
+            select_rect_bitmap: Default::default(),
+            last_capture: None,
+            recovery_bitmap: Default::default(),
+            hotbar_region: None,
+            sentinel_region: None,
+            clients: Vec::new(),
+            hotbar_counts: Arc::new(Mutex::new(BTreeMap::new())),
+            hotbar_read_at: None,
+            hotbar_reading: Arc::new(AtomicBool::new(false)),
+            scanned_str: None,
+            selected: Selection::default(),
+
+            grower: Grower::new(
+                Arc::new(SystemClock),
+                Arc::new(SystemInputActivity),
+                Arc::new(SystemDesktop),
+            ),
+            capture: Profile::default().capture_schedule(),
+        }
+    }
+}
+
+impl std::fmt::Debug for AppState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AppState")
+    }
+}
+
+impl BasicApp {
+    fn on_init(&self) {
+        self.timer_1s.start();
+        self.init_garden_list();
+        let mut state = self.state.lock().unwrap();
+        self.init_select_area_bgimg(&mut state);
+        self.load_profile(&mut state);
+        self.update_rich_text(&state);
+        self.update_hotbar_text(&state);
+        self.update_progress_text(&state);
+        self.update_garden_list(&state);
+    }
+
+    fn on_profile_select(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.load_profile(&mut state);
+        self.refresh_logic_and_ui(&mut state);
+    }
+
+    fn load_profile(&self, state: &mut AppState) {
+        let Some(name) = self.profile_combo.selection_string() else {
+            return;
+        };
+        let profile = Profile::load(&name);
+
+        state.select_region = profile.select_region;
+        state.hotbar_region = profile.hotbar_region;
+        state.sentinel_region = profile.sentinel_region;
+        state.hotbar_counts.lock().unwrap().clear();
+        state.hotbar_read_at = None;
+        self.num_rounds_input
+            .set_text(&profile.num_rounds.to_string());
+        self.goal_plants_input
+            .set_text(&profile.goal_plants.to_string());
+        self.goal_minutes_input
+            .set_text(&profile.goal_minutes.to_string());
+        self.stop_at_input.set_text(&profile.stop_at);
+        self.until_out_of_stock_check
+            .set_check_state(match profile.until_out_of_stock {
+                false => nwg::CheckBoxState::Unchecked,
+                true => nwg::CheckBoxState::Checked,
+            });
+        self.num_growing_objects_input
+            .set_text(&profile.num_objects.to_string());
+        self.extra_delay_sec_input
+            .set_text(&profile.extra_delay_secs.to_string());
+        self.background_input_check
+            .set_check_state(match profile.input_method {
+                InputMethod::Foreground => nwg::CheckBoxState::Unchecked,
+                InputMethod::Background => nwg::CheckBoxState::Checked,
+            });
+        self.per_plot_check.set_check_state(match profile.per_plot {
+            false => nwg::CheckBoxState::Unchecked,
+            true => nwg::CheckBoxState::Checked,
+        });
+        state.capture = profile.capture_schedule();
+        self.timer_1s.set_interval(state.capture.min());
+        state.profile = profile;
+        self.refresh_clients(state);
+        self.convert_legacy_areas(state);
+    }
+
+    /// Areas used to be saved in screen coordinates. Make them follow the
+    /// game window from where it is now.
+    fn convert_legacy_areas(&self, state: &mut AppState) {
+        let profile = &state.profile;
+        if profile.select_rect.is_none()
+            && profile.hotbar_rect.is_none()
+            && profile.sentinel_rect.is_none()
+        {
+            return;
+        }
+        let Some(area) = self.game_area(state) else {
+            log::error!(
+                "Can't find the game window, the areas saved in the profile need to be set again"
+            );
+            return;
+        };
+
+        let profile = &state.profile;
+        let convert = |rect: Option<Rectangle<f64>>| {
+            rect.map(|rect| WindowRegion::from_screen(rect, area, Anchor::Offset))
+        };
+        let (select, hotbar, sentinel) = (
+            convert(profile.select_rect),
+            convert(profile.hotbar_rect),
+            convert(profile.sentinel_rect),
+        );
+        state.select_region = state.select_region.or(select);
+        state.hotbar_region = state.hotbar_region.or(hotbar);
+        state.sentinel_region = state.sentinel_region.or(sentinel);
+    }
+
+    fn profile_from_inputs(&self, state: &AppState) -> Result<Profile, ConfigError> {
+        let number = |input: &nwg::TextInput, e: ConfigError| input.text().parse().map_err(|_| e);
+        Ok(Profile {
+            select_region: state.select_region,
+            hotbar_region: state.hotbar_region,
+            sentinel_region: state.sentinel_region,
+            num_rounds: self.num_rounds_input.text().parse::<usize>().unwrap(),
+            goal_plants: number(&self.goal_plants_input, ConfigError::InvalidGoalPlants)?,
+            goal_minutes: number(&self.goal_minutes_input, ConfigError::InvalidGoalMinutes)?,
+            stop_at: self.stop_at_input.text(),
+            until_out_of_stock: self.until_out_of_stock_check.check_state()
+                == nwg::CheckBoxState::Checked,
+            num_objects: self
+                .num_growing_objects_input
+                .text()
+                .parse::<usize>()
+                .unwrap(),
+            extra_delay_secs: self.extra_delay_sec_input.text().parse::<usize>().unwrap(),
+            input_method: self.input_method(),
+            per_plot: self.per_plot_check.check_state() == nwg::CheckBoxState::Checked,
+            // the rest can be only set in the profile file
+            ..state.profile.clone()
+        })
+    }
+
+    fn save_profile(&self, profile: &Profile) {
+        let Some(name) = self.profile_combo.selection_string() else {
+            return;
+        };
+        if let Err(e) = profile.save(&name) {
+            log::error!("Failed to save profile {name}: {e}");
+        }
+    }
+
+    fn input_method(&self) -> InputMethod {
+        match self.background_input_check.check_state() {
+            nwg::CheckBoxState::Checked => InputMethod::Background,
+            _ => InputMethod::Foreground,
+        }
+    }
+
+    fn on_select_area_btn(&self) {
+        let Some(region) = self.pick_region() else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        state.select_region = Some(region);
+        self.refresh_logic_and_ui(&mut state);
+    }
+
+    fn on_hotbar_area_btn(&self) {
+        let Some(region) = self.pick_region() else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        state.hotbar_region = Some(region);
+        state.hotbar_read_at = None;
+        self.refresh_logic_and_ui(&mut state);
+    }
+
+    fn on_sentinel_area_btn(&self) {
+        let Some(region) = self.pick_region() else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        state.sentinel_region = Some(region);
+        self.refresh_logic_and_ui(&mut state);
+    }
+
+    /// Let the user select an area of the game window. None if cancelled.
+    fn pick_region(&self) -> Option<WindowRegion> {
+        let rect = self.pick_area()?;
+        let state = self.state.lock().unwrap();
+        let Some(area) = self.game_area(&state) else {
+            nwg::modal_info_message(
+                &self.window,
+                "Error",
+                "Can't find the game window, start the game or pick the client first",
+            );
+            return None;
+        };
+        Some(WindowRegion::from_screen(
+            rect,
+            area,
+            state.profile.region_anchor,
+        ))
+    }
+
+    /// Client area of the picked game client, in the screenshot coordinates
+    fn game_area(&self, state: &AppState) -> Option<Rectangle<f64>> {
+        let selected = self
+            .client_combo
+            .selection()
+            .and_then(|index| state.clients.get(index));
+        let hwnd = match selected {
+            Some(client) if window::is_alive(client.hwnd) => client.hwnd,
+            // the client could have been restarted
+            _ => {
+                let mut query = state.profile.window_query();
+                query.pid = selected.map(|client| client.pid);
+                query.find()?.hwnd
+            }
+        };
+        let client_rect = window::client_rect(hwnd)?;
+        Some(geometry::screen_to_image(
+            client_rect,
+            screenshot::virtual_screen(),
+        ))
+    }
+
+    /// Let the user select an area of the screen. None if cancelled.
+    fn pick_area(&self) -> Option<Rectangle<f64>> {
+        let screenshot = Screenshot::take();
+        match self.cropper.lock().unwrap().apply(&screenshot) {
+            Ok(Some(rect)) if rect.w > 0.0 && rect.h > 0.0 => Some(rect),
+            Err(e) => {
+                nwg::modal_info_message(&self.window, "Error", &format!("{:?}", e));
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn on_startstop_btn(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        if self.startstop_btn.text() == "Start" {
+            let configured = self
+                .profile_from_inputs(&state)
+                .and_then(|profile| Ok((GrowerConfig::try_from(&profile)?, profile)));
+            match configured {
+                Ok((mut config, profile)) => {
+                    config.window.pid = self.selected_client(&state);
+                    self.start(&mut state, config, &profile)
+                }
+                Err(e) => {
+                    nwg::modal_info_message(&self.window, "Error", &e.to_string());
+                    return;
+                }
+            }
+        } else {
+            self.stop(&mut state);
+        }
+        self.refresh_logic_and_ui(&mut state);
+    }
+
+    fn on_count_btn(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.select_region.is_none() {
+            let e = ConfigError::MissingSelectArea;
+            nwg::modal_info_message(&self.window, "Error", &e.to_string());
+            return;
+        }
+
+        let mut window = state.profile.window_query();
+        window.pid = self.selected_client(&state);
+        state.grower.count_plots(self.input_method(), window);
+        self.startstop_btn.set_text("Stop");
+        self.set_inputs_enabled(false);
+        self.pause_btn.set_enabled(false);
+        self.restart_timer();
+        self.refresh_logic_and_ui(&mut state);
+    }
+
+    fn on_find_clients_btn(&self) {
+        let mut state = self.state.lock().unwrap();
+        self.refresh_clients(&mut state);
+    }
+
+    /// List all game clients matching the profile, keeping the selected one
+    fn refresh_clients(&self, state: &mut AppState) {
+        let selected = self.selected_client(state);
+        state.clients = state.profile.window_query().find_all();
+        self.client_combo
+            .set_collection(state.clients.iter().map(|w| w.to_string()).collect());
+        let index = state
+            .clients
+            .iter()
+            .position(|window| Some(window.pid) == selected)
+            .or((!state.clients.is_empty()).then_some(0));
+        self.client_combo.set_selection(index);
+    }
+
+    /// Process id of the client picked in the window
+    fn selected_client(&self, state: &AppState) -> Option<u32> {
+        let index = self.client_combo.selection()?;
+        state.clients.get(index).map(|window| window.pid)
+    }
+
+    fn on_recovery_resume_btn(&self) {
+        self.recover(RecoveryChoice::Resume);
+    }
+
+    fn on_recovery_skip_btn(&self) {
+        self.recover(RecoveryChoice::Skip);
+    }
+
+    fn on_recovery_stop_btn(&self) {
+        self.recover(RecoveryChoice::Stop);
+    }
+
+    fn recover(&self, choice: RecoveryChoice) {
+        let state = self.state.lock().unwrap();
+        state.grower.recover(choice);
+        self.recovery_window.set_visible(false);
+    }
+
+    /// Show the recovery prompt while the grower waits for an answer
+    fn update_recovery_prompt(&self, state: &mut AppState) {
+        let prompt = state.grower.recovery.lock().unwrap().clone();
+        let Some(prompt) = prompt else {
+            self.recovery_window.set_visible(false);
+            return;
+        };
+
+        if !self.recovery_window.visible() {
+            if let Some(image) = &state.last_capture {
+                let bmpdata = bmp::encode(&image.view());
+                let bitmap = &mut state.recovery_bitmap;
+                nwg::Bitmap::builder()
+                    .source_bin(Some(&bmpdata))
+                    .build(bitmap)
+                    .unwrap();
+                self.recovery_img.set_bitmap(Some(bitmap));
+            }
+            self.recovery_window.set_visible(true);
+        }
+
+        let left = prompt
+            .deadline
+            .saturating_duration_since(state.grower.clock.now());
+        let auto_choice = match prompt.auto_choice {
+            RecoveryChoice::Resume => "resuming",
+            RecoveryChoice::Skip => "skipping the plot",
+            RecoveryChoice::Stop => "stopping",
+        };
+        self.recovery_label.set_text(&format!(
+            "{}. Currently selected as above.\nNo answer in {} means {auto_choice}.",
+            prompt.reason,
+            format_duration(left)
+        ));
+    }
+
+    fn on_pause_btn(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.grower.is_paused() {
+            state.grower.resume();
+        } else {
+            state.grower.pause();
+        }
+        self.refresh_logic_and_ui(&mut state);
+    }
+
+    fn on_tick_1s(&self) {
+        let mut state = self.state.lock().unwrap();
+        if self.capture_due(&state) {
+            self.refresh_logic_and_ui(&mut state);
+        } else {
+            self.sync_with_grower(&mut state);
+            self.update_rich_text(&state);
+            self.update_hotbar_text(&state);
+            self.update_progress_text(&state);
+            self.update_garden_list(&state);
+        }
+    }
+
+    /// The timer ticks at the minimal capture interval, but there's no need to
+    /// read the screen that often unless something is about to happen
+    fn capture_due(&self, state: &AppState) -> bool {
+        let grower = &state.grower;
+        if grower.awaiting_read() {
+            return true;
+        }
+
+        let now = grower.clock.now();
+        let next_action = {
+            let stats = grower.stats.lock().unwrap();
+            let next_action = grower.garden.lock().unwrap().next_action(&stats);
+            next_action.map(|at| at.saturating_duration_since(now))
+        };
+        let grower_state = grower.machine.lock().unwrap().state().clone();
+        let interval = state.capture.interval(&grower_state, next_action);
+        state.capture.is_due(now, interval)
+    }
+
+    fn init_select_area_bgimg(&self, state: &mut AppState) {
+        let dimensions = self.select_area_bgimg.size();
+        // we want the background black
+        let background = Image::new(dimensions.0, dimensions.1, PixelFormat::Bgr);
+        let bmpdata = bmp::encode(&background.view());
+
+        let bitmap = &mut state.select_rect_bitmap;
+        nwg::Bitmap::builder()
+            .source_bin(Some(&bmpdata))
+            .build(bitmap)
+            .unwrap();
+
+        self.select_area_bgimg.set_bitmap(Some(bitmap));
+    }
+
+    fn update_rich_text(&self, state: &AppState) {
+        let mut rbuilder = richbuilder::RichBuilder::new(&self.rich_text_box);
+
+        rbuilder.append("Gardenbot is ", nwg::CharFormat::default());
+        if state.grower.is_running() {
+            rbuilder.append(
+                "online",
+                nwg::CharFormat {
+                    effects: Some(nwg::CharEffects::BOLD),
+                    text_color: Some([0, 140, 0]),
+                    ..Default::default()
+                },
+            );
+        } else {
+            rbuilder.append(
+                "offline",
+                nwg::CharFormat {
+                    effects: Some(nwg::CharEffects::BOLD),
+                    text_color: Some([200, 0, 0]),
+                    ..Default::default()
+                },
+            );
+        }
+        rbuilder.append("\n", nwg::CharFormat::default());
+        if let Some(scanned_str) = &state.scanned_str {
+            let machine = state.grower.machine.lock().unwrap();
+            match machine.state() {
+                GrowerState::Idle => rbuilder.append("\n", nwg::CharFormat::default()),
+                GrowerState::Stopped { reason } => rbuilder.append(
+                    &format!("Stopped: {reason}\n"),
+                    nwg::CharFormat {
+                        effects: Some(nwg::CharEffects::BOLD),
+                        text_color: Some([200, 0, 0]),
+                        ..Default::default()
+                    },
+                ),
+                grower_state => {
+                    let now = state.grower.clock.now();
+                    let transition = machine.last_transition().unwrap();
+                    let secs = (now - transition.at).as_secs();
+                    rbuilder.append(
+                        &format!("{grower_state} for {secs}s"),
+                        nwg::CharFormat::default(),
+                    );
+                    let stats = state.grower.stats.lock().unwrap();
+                    let next_action = state.grower.garden.lock().unwrap().next_action(&stats);
+                    if let (GrowerState::Waiting, Some(at)) = (grower_state, next_action) {
+                        let next = at.saturating_duration_since(now);
+                        rbuilder.append(
+                            &format!(", next action in ~{}", format_duration(next)),
+                            nwg::CharFormat::default(),
+                        );
+                    }
+                    rbuilder.append("\n", nwg::CharFormat::default());
+                }
+            }
+            match machine.last_transition() {
+                Some(transition) if machine.state().is_running() => rbuilder.append(
+                    &format!("{}\n", transition.reason),
+                    nwg::CharFormat::default(),
+                ),
+                _ => rbuilder.append("\n", nwg::CharFormat::default()),
+            }
+            drop(machine);
+
+            rbuilder.append("Scanned: ", nwg::CharFormat::default());
+            if scanned_str.trim().is_empty() {
+                rbuilder.append("<nothing>", nwg::CharFormat::default());
+            } else {
+                rbuilder.append(scanned_str, nwg::CharFormat::default());
+                rbuilder.append(" -> ", nwg::CharFormat::default());
+
+                use CurrentlySelected as C;
+                match state.selected.state {
+                    C::None => rbuilder.append("None", nwg::CharFormat::default()),
+                    C::Growing => rbuilder.append("Growing", nwg::CharFormat::default()),
+                    C::Thisty => rbuilder.append(
+                        "Thirsty",
+                        nwg::CharFormat {
+                            effects: Some(nwg::CharEffects::BOLD),
+                            text_color: Some([40, 130, 170]),
+                            ..Default::default()
+                        },
+                    ),
+                    C::Hungry => rbuilder.append(
+                        "Hungry",
+                        nwg::CharFormat {
+                            effects: Some(nwg::CharEffects::BOLD),
+                            text_color: Some([126, 98, 86]),
+                            ..Default::default()
+                        },
+                    ),
+                    C::Ripe => rbuilder.append(
+                        "Ripe",
+                        nwg::CharFormat {
+                            effects: Some(nwg::CharEffects::BOLD),
+                            text_color: Some([0, 140, 0]),
+                            ..Default::default()
+                        },
+                    ),
+                }
+            }
+        } else {
+            rbuilder.append(
+                "No entity area selected\n",
+                nwg::CharFormat {
+                    effects: Some(nwg::CharEffects::BOLD),
+                    text_color: Some([100, 100, 100]),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    fn refresh_logic_and_ui(&self, state: &mut AppState) {
+        self.refresh_logic(state);
+        self.update_rich_text(state);
+        self.update_hotbar_text(state);
+        self.update_progress_text(state);
+        self.update_garden_list(state);
+    }
+
+    fn update_progress_text(&self, state: &AppState) {
+        let mut rbuilder = richbuilder::RichBuilder::new(&self.progress_text);
+        let progress = state.grower.progress.lock().unwrap();
+        match progress.as_ref() {
+            Some(progress) => {
+                let summary = progress.summary(state.grower.clock.now());
+                rbuilder.append(&format!("Goals: {summary}"), nwg::CharFormat::default());
+            }
+            None => rbuilder.append("Goals: not started", nwg::CharFormat::default()),
+        }
+    }
+
+    fn update_hotbar_text(&self, state: &AppState) {
+        let mut rbuilder = richbuilder::RichBuilder::new(&self.hotbar_text);
+        if state.hotbar_region.is_none() || state.profile.hotbar_slots.is_empty() {
+            rbuilder.append("Hotbar: not watched", nwg::CharFormat::default());
+            return;
+        }
+
+        let num_objects = self
+            .num_growing_objects_input
+            .text()
+            .parse::<usize>()
+            .unwrap_or(0);
+        rbuilder.append("Hotbar:", nwg::CharFormat::default());
+        let hotbar_counts = state.hotbar_counts.lock().unwrap();
+        for hotbar_slot in &state.profile.hotbar_slots {
+            rbuilder.append(
+                &format!(" {} ", hotbar_slot.name),
+                nwg::CharFormat::default(),
+            );
+            match hotbar_counts.get(&hotbar_slot.slot) {
+                Some(&count) if hotbar_slot.is_low(count, num_objects) => rbuilder.append(
+                    &count.to_string(),
+                    nwg::CharFormat {
+                        effects: Some(nwg::CharEffects::BOLD),
+                        text_color: Some([200, 0, 0]),
+                        ..Default::default()
+                    },
+                ),
+                Some(count) => rbuilder.append(&count.to_string(), nwg::CharFormat::default()),
+                None => rbuilder.append("?", nwg::CharFormat::default()),
+            }
+        }
+    }
+
+    fn init_garden_list(&self) {
+        let columns = [
+            ("#", 30),
+            ("Crop", 70),
+            ("State", 55),
+            ("Since", 50),
+            ("Next", 45),
+        ];
+        for (index, (text, width)) in columns.into_iter().enumerate() {
+            self.garden_list.insert_column(nwg::InsertListViewColumn {
+                index: Some(index as i32),
+                fmt: None,
+                width: Some(width),
+                text: Some(text.to_string()),
+            });
+        }
+        self.garden_list.set_headers_enabled(true);
+    }
+
+    fn update_garden_list(&self, state: &AppState) {
+        let now = state.grower.clock.now();
+        let stats = state.grower.stats.lock().unwrap();
+        let garden = state.grower.garden.lock().unwrap();
+
+        self.garden_list.clear();
+        for (i, plot) in garden.plots().iter().enumerate() {
+            let marker = if i == garden.cursor() { ">" } else { "" };
+            let next = match plot.predicted_action(&stats) {
+                Some(at) if at > now => format!("~{}", format_duration(at - now)),
+                Some(_) => "due".to_string(),
+                None => String::new(),
+            };
+            let (plot_state, since) = match (plot.last_seen, plot.since) {
+                (None, _) => ("?", String::new()),
+                (Some(_), since) => {
+                    use CurrentlySelected as C;
+                    let name = match plot.state {
+                        C::None => "Empty",
+                        C::Growing => "Growing",
+                        C::Thisty => "Thirsty",
+                        C::Hungry => "Hungry",
+                        C::Ripe => "Ripe",
+                    };
+                    let since = since.map(|at| format_duration(now - at));
+                    (name, since.unwrap_or_default())
+                }
+            };
+            self.garden_list.insert_items_row(
+                None,
+                &[
+                    format!("{marker}{}", i + 1),
+                    plot.crop.clone(),
+                    plot_state.to_string(),
+                    since,
+                    next,
+                ],
+            );
+        }
+    }
+
+    fn refresh_logic(&self, state: &mut AppState) {
+        self.sync_with_grower(state);
+
+        let now = state.grower.clock.now();
+        state.capture.captured(now);
+        // the areas follow the game window wherever it is now, and only they
+        // are captured, not the whole screen
+        let area = self.game_area(state);
+        self.read_hotbar(state, area);
+        if let Some(rect) = screen_rect(state.sentinel_region, area) {
+            let screenshot = Screenshot::take_region(rect);
+            state
+                .grower
+                .frame_captured(Frame::of(&screenshot.image.view()));
+        }
+        state.last_capture = self.refresh_select_rect(state, area);
+        let text = state.last_capture.as_ref().and_then(|image| {
+            ocr_text(&image.view())
+                .map_err(|e| log::error!("Can't read the selected entity: {e}"))
+                .ok()
+        });
+        if let Some(text) = text {
+            let scanned_str = state.scanned_str.insert(text.replace(['\n', '\r'], ""));
+
+            let matching_selection = Selection::from(scanned_str.as_str());
+
+            if matching_selection != state.selected {
+                state.selected = matching_selection.clone();
+                state.grower.selection_changed(matching_selection);
+            }
+            state.grower.scanned();
+        } else {
+            state.scanned_str = None;
+        };
+    }
+
+    /// Pick up whatever the grower finished on its own
+    fn sync_with_grower(&self, state: &mut AppState) {
+        if let Some(num_plots) = state.grower.counted_plots.lock().unwrap().take() {
+            self.num_growing_objects_input
+                .set_text(&num_plots.to_string());
+        }
+
+        self.update_recovery_prompt(state);
+
+        // the grower could have paused or resumed itself
+        let pause_text = if state.grower.is_paused() {
+            "Resume"
+        } else {
+            "Pause"
+        };
+        if self.pause_btn.text() != pause_text {
+            self.pause_btn.set_text(pause_text);
+        }
+
+        // the session could have finished on its own
+        if !state.grower.is_running() && self.startstop_btn.text() == "Stop" {
+            self.stop(state);
+        }
+    }
+
+    /// Read the stack counts of all watched hotbar slots every
+    /// [`HOTBAR_READ_INTERVAL`]. The OCR runs on its own thread and the counts
+    /// show up once it's done.
+    fn read_hotbar(&self, state: &mut AppState, area: Option<Rectangle<f64>>) {
+        let (Some(region), Some(area)) = (state.hotbar_region, area) else {
+            return;
+        };
+        let now = state.grower.clock.now();
+        if state
+            .hotbar_read_at
+            .is_some_and(|at| now - at < HOTBAR_READ_INTERVAL)
+        {
+            return;
+        }
+        let hotbar_rect = region.to_screen(area);
+        let counts_rect = hotbar::counts_rect(hotbar_rect);
+        let screen = screenshot::full_area();
+        let (Some(capture_rect), Some(counts_capture)) = (
+            geometry::clip(hotbar_rect, screen),
+            geometry::clip(counts_rect, screen),
+        ) else {
+            return;
+        };
+        if state.hotbar_reading.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        state.hotbar_read_at = Some(now);
+        // one capture and one OCR run for all the slots
+        let screenshot = Screenshot::take_region(capture_rect);
+
+        let watched: Vec<u8> = state
+            .profile
+            .hotbar_slots
+            .iter()
+            .map(|hotbar_slot| hotbar_slot.slot)
+            .collect();
+        let hotbar_counts = state.hotbar_counts.clone();
+        let reading = ClearOnDrop(state.hotbar_reading.clone());
+        let grower = state.grower.clone();
+        std::thread::spawn(move || {
+            // let the next read start however this one ends
+            let _reading = reading;
+            let mut counts = match screenshot.view(counts_capture) {
+                Some(image) => match ocr_counts(&image) {
+                    Ok(tsv) => hotbar::parse_counts(&tsv, counts_rect, counts_capture),
+                    Err(e) => {
+                        log::error!("Can't read the hotbar: {e}");
+                        return;
+                    }
+                },
+                None => BTreeMap::new(),
+            };
+            counts.retain(|slot, _| watched.contains(slot));
+            hotbar_counts.lock().unwrap().clone_from(&counts);
+            grower.hotbar_read(counts);
+        });
+    }
+
+    fn refresh_select_rect(
+        &self,
+        state: &mut AppState,
+        area: Option<Rectangle<f64>>,
+    ) -> Option<Image> {
+        let max_dimensions = self.select_area_bgimg.size();
+        let mut rect = state.select_region?.to_screen(area?);
+        rect.w = f64::min(rect.w, max_dimensions.0 as f64);
+        rect.h = f64::min(rect.h, max_dimensions.1 as f64);
+        let rect = geometry::clip(rect, screenshot::full_area())?;
+
+        let screenshot = Screenshot::take_region(rect);
+        let bmpdata = bmp::encode(&screenshot.image.view());
+
+        let bitmap = &mut state.select_rect_bitmap;
+        nwg::Bitmap::builder()
+            .source_bin(Some(&bmpdata))
+            .build(bitmap)
+            .unwrap();
+
+        self.select_area_img.set_bitmap(Some(bitmap));
+        self.select_area_img.set_size(rect.w, rect.h);
+        self.select_area_img.set_visible(true);
+
+        Some(screenshot.image)
+    }
+
+    fn start(&self, state: &mut AppState, config: GrowerConfig, profile: &Profile) {
+        state.grower.start(config);
+        self.save_profile(profile);
+
+        self.startstop_btn.set_text("Stop");
+        self.set_inputs_enabled(false);
+        self.restart_timer();
+    }
+
+    fn stop(&self, state: &mut AppState) {
+        state.grower.stop();
+
+        self.startstop_btn.set_text("Start");
+        self.set_inputs_enabled(true);
+        self.restart_timer();
+    }
+
+    fn set_inputs_enabled(&self, enabled: bool) {
+        self.pause_btn.set_text("Pause");
+        self.pause_btn.set_enabled(!enabled);
+        self.num_rounds_input.set_readonly(!enabled);
+        self.num_rounds_input.set_enabled(enabled);
+        self.goal_plants_input.set_readonly(!enabled);
+        self.goal_plants_input.set_enabled(enabled);
+        self.goal_minutes_input.set_readonly(!enabled);
+        self.goal_minutes_input.set_enabled(enabled);
+        self.stop_at_input.set_readonly(!enabled);
+        self.stop_at_input.set_enabled(enabled);
+        self.until_out_of_stock_check.set_enabled(enabled);
+        self.num_growing_objects_input.set_readonly(!enabled);
+        self.num_growing_objects_input.set_enabled(enabled);
+        self.count_btn.set_enabled(enabled);
+        self.extra_delay_sec_input.set_readonly(!enabled);
+        self.extra_delay_sec_input.set_enabled(enabled);
+        self.background_input_check.set_enabled(enabled);
+        self.per_plot_check.set_enabled(enabled);
+        self.profile_combo.set_enabled(enabled);
+        self.select_area_btn.set_enabled(enabled);
+        self.hotbar_area_btn.set_enabled(enabled);
+        self.sentinel_area_btn.set_enabled(enabled);
+        self.client_combo.set_enabled(enabled);
+        self.find_clients_btn.set_enabled(enabled);
+    }
+
+    fn restart_timer(&self) {
+        self.timer_1s.stop();
+        self.timer_1s.start();
+    }
+
+    fn on_new_bot_btn(&self) {
+        if let Err(e) = open_bot_window() {
+            nwg::modal_info_message(&self.window, "Error", &format!("{:?}", e));
+        }
+    }
+
+    fn on_close(&self) {
+        self.timer_1s.stop();
+        self.recovery_window.set_visible(false);
+        self.state
+            .lock()
+            .unwrap()
+            .grower
+            .shutdown(GROWER_SHUTDOWN_TIMEOUT);
+        if OPEN_BOTS.fetch_sub(1, Ordering::Relaxed) == 1 {
+            nwg::stop_thread_dispatch();
+        }
+    }
+}
+
+/// Pixels of the region on the screen, given the game's client area
+fn screen_rect(
+    region: Option<WindowRegion>,
+    area: Option<Rectangle<f64>>,
+) -> Option<Rectangle<u32>> {
+    geometry::clip(region?.to_screen(area?), screenshot::full_area())
+}
+
+/// Clears the flag when dropped
+struct ClearOnDrop(Arc<AtomicBool>);
+
+impl Drop for ClearOnDrop {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+fn ocr_text(image: &ImageView) -> io::Result<String> {
+    ocr(
+        image,
+        &[
+            "-color-threshold",
+            "sRGB(70,70,70)-sRGB(230,210,160)",
+            "-negate",
+        ],
+        &[],
+    )
+}
+
+/// Read the hotbar stack counts, drawn in white. Returns tesseract's TSV
+/// output with the position of every number.
+fn ocr_counts(image: &ImageView) -> io::Result<String> {
+    ocr(
+        image,
+        &["-threshold", "80%", "-negate"],
+        &[
+            "--psm",
+            "11",
+            "-c",
+            "tessedit_char_whitelist=0123456789",
+            "tsv",
+        ],
+    )
+}
+
+/// Enhance the image with imagemagick's filters, then run tesseract on it
+fn ocr(image: &ImageView, filters: &[&str], tesseract_args: &[&str]) -> io::Result<String> {
+    let bmpdata = bmp::encode(image);
+    let mut convert = Command::new("C:\\Program Files\\ImageMagick\\convert.exe");
+    convert.arg("fd:0").args(filters).arg("fd:1");
+    let processed = run_filter(&mut convert, &bmpdata)
+        .map_err(|e| io::Error::other(format!("imagemagick (image enhancing) failed: {e}")))?;
+
+    let mut tesseract = Command::new("C:\\Program Files\\Tesseract-OCR\\tesseract.exe");
+    tesseract.args(["stdin", "stdout"]).args(tesseract_args);
+    let text = run_filter(&mut tesseract, &processed)
+        .map_err(|e| io::Error::other(format!("tesseract failed: {e}")))?;
+    Ok(String::from_utf8_lossy(&text).into_owned())
+}
+
+/// Run the command with `input` on its stdin and return its stdout
+fn run_filter(cmd: &mut Command, input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut proc = cmd
+        .creation_flags(CREATE_NO_WINDOW)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // closing stdin lets the command finish
+    if let Some(mut stdin) = proc.stdin.take() {
+        stdin.write_all(input)?;
+    }
+
+    let output = proc.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Open a new bot window, e.g. for another game client. Each one has its own
+/// grower, profile, game client and status.
+fn open_bot_window() -> Result<(), nwg::NwgError> {
+    let app = BasicApp::build_ui(Default::default())?;
+
+    unsafe {
+        let h_instance = GetModuleHandleW(std::ptr::null());
+        assert!(!h_instance.is_null());
+        let icon = LoadIconW(h_instance, MAKEINTRESOURCEW(32512));
+        assert!(!icon.is_null());
+        let nwg::ControlHandle::Hwnd(win_hwnd) = app.window.handle else {
+            unreachable!();
+        };
+        // WM_SETICON doesn't update taskbar icon on Windows 11, so update the Window Class instead
+        SetClassLongPtrA(win_hwnd, GCLP_HICON, icon as _);
+        SetClassLongPtrA(win_hwnd, GCLP_HICONSM, icon as _);
+    }
+
+    let num = BOTS.with(|bots| bots.borrow().len()) + 1;
+    if num > 1 {
+        let title = format!("Gardenbot {num}");
+        app.window.set_text(&title);
+        app.recovery_window
+            .set_text(&format!("{title} - what now?"));
+        let (x, y) = app.window.position();
+        app.window.set_position(x + 285 * (num as i32 - 1), y);
+
+        // start with a different profile, so the bots don't overwrite each other's
+        let num_profiles = app.profile_combo.len();
+        if num_profiles > 1 {
+            app.profile_combo
+                .set_selection(Some((num - 1) % num_profiles));
+            app.on_profile_select();
+        }
+    }
+
+    OPEN_BOTS.fetch_add(1, Ordering::Relaxed);
+    BOTS.with(|bots| bots.borrow_mut().push(app));
+    Ok(())
+}
+
+pub fn run() {
+    // print messages in the parent console, if any
+    unsafe { winapi::um::wincon::AttachConsole(u32::MAX) };
+
+    log::set_verbose(std::env::args().any(|arg| arg == "--verbose"));
+    if std::env::args().any(|arg| arg == "--bench-capture") {
+        bench::capture();
+        return;
+    }
+
+    // don't leave the game with keys pressed or the focus stolen once the
+    // GUI is gone. A panic on any other thread only ends that thread, and a
+    // grower thread cleans up after itself while unwinding.
+    let gui_thread = std::thread::current().id();
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().id() == gui_thread {
+            grower::shutdown_all(GROWER_SHUTDOWN_TIMEOUT);
+        }
+        default_hook(info);
+    }));
+
+    // TODO: is this needed?
+    unsafe {
+        winapi::um::shellscalingapi::SetProcessDpiAwareness(
+            winapi::um::shellscalingapi::PROCESS_DPI_UNAWARE,
+        );
+    }
+
+    nwg::init().expect("Failed to init Native Windows GUI");
+
+    let mut font = nwg::Font::default();
+    nwg::Font::builder()
+        .family("Segoe UI")
+        .size(16)
+        .build(&mut font)
+        .expect("Failed to build font");
+
+    nwg::Font::set_global_default(Some(font));
+
+    open_bot_window().expect("Failed to build UI");
+
+    nwg::dispatch_thread_events();
+
+    println!("Exiting");
+}
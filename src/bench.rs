@@ -0,0 +1,94 @@
+use std::time::{Duration, Instant};
+
+use winapi::shared::minwindef::FILETIME;
+use winapi::um::processthreadsapi::{GetCurrentProcess, GetProcessTimes};
+use winapi::um::psapi::{K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+
+use crate::screenshot::{self, Rectangle, Screenshot};
+
+const RUNS: u32 = 50;
+
+/// Margin used by the margin benchmark, in pixels
+const MARGIN: u32 = 16;
+
+/// Compare the cost of capturing the whole screen with capturing just the
+/// select area, and print the results
+pub fn capture() {
+    let screen = screenshot::full_area();
+    // about the size of a nameplate in the middle of the screen
+    let nameplate = Rectangle {
+        x: screen.w / 2,
+        y: screen.h / 2,
+        w: 255.min(screen.w / 2),
+        h: 30.min(screen.h / 2),
+    };
+
+    println!(
+        "Capturing {RUNS} times each, screen is {}x{}",
+        screen.w, screen.h
+    );
+    // the peak working set only ever grows, so go from the smallest capture
+    // to the largest one to see what each of them adds
+    report("255x30 region", || Screenshot::take_region(nameplate));
+    report(&format!("255x30 region + {MARGIN}px margin"), || {
+        Screenshot::take_region_with_margin(nameplate, MARGIN)
+    });
+    report("whole screen", Screenshot::take);
+}
+
+fn report(name: &str, take: impl Fn() -> Screenshot) {
+    let mut total = Duration::ZERO;
+    let mut bytes = 0;
+    let peak_before = peak_working_set();
+    let cpu_before = cpu_time();
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let screenshot = take();
+        total += start.elapsed();
+        bytes = screenshot.image.size_bytes();
+    }
+    let cpu = cpu_time() - cpu_before;
+    let peak = peak_working_set();
+    println!(
+        "{name}: {:.2} ms, {:.2} ms CPU, {} KiB per capture; peak working set {} MiB (+{} KiB)",
+        total.as_secs_f64() * 1000.0 / RUNS as f64,
+        cpu.as_secs_f64() * 1000.0 / RUNS as f64,
+        bytes / 1024,
+        peak / (1024 * 1024),
+        (peak - peak_before) / 1024
+    );
+}
+
+/// User and kernel time this process has used so far
+fn cpu_time() -> Duration {
+    let zero = FILETIME {
+        dwLowDateTime: 0,
+        dwHighDateTime: 0,
+    };
+    let (mut creation, mut exit, mut kernel, mut user) = (zero, zero, zero, zero);
+    unsafe {
+        GetProcessTimes(
+            GetCurrentProcess(),
+            &mut creation,
+            &mut exit,
+            &mut kernel,
+            &mut user,
+        )
+    };
+    filetime_duration(kernel) + filetime_duration(user)
+}
+
+/// FILETIME counts in 100ns steps
+fn filetime_duration(time: FILETIME) -> Duration {
+    let ticks = (time.dwHighDateTime as u64) << 32 | time.dwLowDateTime as u64;
+    Duration::from_nanos(ticks * 100)
+}
+
+/// Largest working set this process had so far, in bytes
+fn peak_working_set() -> usize {
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    counters.cb = size;
+    unsafe { K32GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) };
+    counters.PeakWorkingSetSize
+}
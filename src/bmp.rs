@@ -1,5 +1,7 @@
 use bytemuck::{Pod, Zeroable};
 
+use crate::image::{ImageView, Orientation, PixelFormat};
+
 #[derive(Copy, Clone, Pod, Zeroable)]
 #[repr(C, packed)]
 pub struct BMPHeader {
@@ -74,3 +76,38 @@ impl InfoHeader {
         }
     }
 }
+
+/// Encode the image as a 24-bit top-down BMP file
+pub fn encode(image: &ImageView) -> Vec<u8> {
+    let (width, height) = (image.width(), image.height());
+    let row_size = (width * 3).next_multiple_of(4) as usize;
+    let row_padding = [0u8; 3];
+    let row_padding = &row_padding[..row_size - width as usize * 3];
+
+    let mut file: Vec<u8> = Vec::with_capacity(
+        core::mem::size_of::<BMPHeader>()
+            + core::mem::size_of::<InfoHeader>()
+            + row_size * height as usize,
+    );
+    file.extend_from_slice(bytemuck::bytes_of(&BMPHeader::new(
+        row_size * height as usize,
+    )));
+    // negative height for the top-down rows
+    file.extend_from_slice(bytemuck::bytes_of(&InfoHeader::new(
+        width,
+        height.wrapping_neg(),
+    )));
+
+    let bgr;
+    let image = if image.format() == PixelFormat::Bgr {
+        *image
+    } else {
+        bgr = image.convert(PixelFormat::Bgr, Orientation::TopDown);
+        bgr.view()
+    };
+    for row in image.rows() {
+        file.extend_from_slice(row);
+        file.extend_from_slice(row_padding);
+    }
+    file
+}
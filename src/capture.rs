@@ -0,0 +1,126 @@
+use std::time::{Duration, Instant};
+
+use crate::state::GrowerState;
+
+/// Decides how often the selected entity area is captured and read. OCR is
+/// expensive, so read rarely when nothing is expected to change.
+#[derive(Debug)]
+pub struct CaptureSchedule {
+    min: Duration,
+    max: Duration,
+    last: Option<Instant>,
+}
+
+impl CaptureSchedule {
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Self {
+            min,
+            max: std::cmp::max(min, max),
+            last: None,
+        }
+    }
+
+    pub fn min(&self) -> Duration {
+        self.min
+    }
+
+    /// Time between captures in the given state. `next_action` is how long
+    /// until any plot is predicted to need something, if known.
+    pub fn interval(&self, state: &GrowerState, next_action: Option<Duration>) -> Duration {
+        use GrowerState as S;
+        match state {
+            // every keypress needs to be confirmed quickly
+            S::Watering | S::Fertilizing | S::Harvesting | S::Replanting | S::Counting => self.min,
+            // check more often as the predicted time gets closer
+            S::Waiting => match next_action {
+                Some(next) => (next / 2).clamp(self.min, self.max),
+                None => self.max,
+            },
+            S::Idle | S::Paused { .. } | S::Stopped { .. } => self.max,
+        }
+    }
+
+    pub fn is_due(&self, now: Instant, interval: Duration) -> bool {
+        self.last.is_none_or(|last| now >= last + interval)
+    }
+
+    pub fn captured(&mut self, now: Instant) {
+        self.last = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, FakeClock};
+    use crate::state::StopReason;
+
+    const MIN: Duration = Duration::from_millis(200);
+    const MAX: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn reads_quickly_while_acting() {
+        let schedule = CaptureSchedule::new(MIN, MAX);
+        for state in [
+            GrowerState::Watering,
+            GrowerState::Fertilizing,
+            GrowerState::Harvesting,
+            GrowerState::Replanting,
+            GrowerState::Counting,
+        ] {
+            assert_eq!(schedule.interval(&state, None), MIN, "{state:?}");
+        }
+    }
+
+    #[test]
+    fn reads_more_often_as_the_next_action_gets_closer() {
+        let schedule = CaptureSchedule::new(MIN, MAX);
+        let waiting = GrowerState::Waiting;
+        assert_eq!(schedule.interval(&waiting, None), MAX);
+        assert_eq!(
+            schedule.interval(&waiting, Some(Duration::from_secs(60))),
+            MAX
+        );
+        assert_eq!(
+            schedule.interval(&waiting, Some(Duration::from_secs(4))),
+            Duration::from_secs(2)
+        );
+        assert_eq!(schedule.interval(&waiting, Some(Duration::ZERO)), MIN);
+    }
+
+    #[test]
+    fn reads_rarely_when_idle() {
+        let schedule = CaptureSchedule::new(MIN, MAX);
+        let stopped = GrowerState::Stopped {
+            reason: StopReason::UserRequest,
+        };
+        assert_eq!(schedule.interval(&GrowerState::Idle, None), MAX);
+        assert_eq!(schedule.interval(&stopped, Some(Duration::ZERO)), MAX);
+    }
+
+    #[test]
+    fn max_is_never_below_min() {
+        let schedule = CaptureSchedule::new(MAX, MIN);
+        assert_eq!(schedule.min(), MAX);
+        assert_eq!(schedule.interval(&GrowerState::Idle, None), MAX);
+        assert_eq!(
+            schedule.interval(&GrowerState::Waiting, Some(Duration::ZERO)),
+            MAX
+        );
+    }
+
+    #[test]
+    fn due_once_the_interval_passed() {
+        let clock = FakeClock::new();
+        let mut schedule = CaptureSchedule::new(MIN, MAX);
+        assert!(schedule.is_due(clock.now(), MAX));
+
+        schedule.captured(clock.now());
+        assert!(!schedule.is_due(clock.now(), MIN));
+        clock.advance(MIN - Duration::from_millis(1));
+        assert!(!schedule.is_due(clock.now(), MIN));
+        clock.advance(Duration::from_millis(1));
+        assert!(schedule.is_due(clock.now(), MIN));
+        assert!(!schedule.is_due(clock.now(), MAX));
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use crate::garden;
+use crate::goals::{self, Goals};
+use crate::hotbar::{self, HotbarSlot};
+use crate::idle::IdlePolicy;
+use crate::input::InputMethod;
+use crate::profile::{CropPlan, EmptySlotPolicy, Profile, RecoveryChoice, ReplantSlot};
+use crate::watchdog::WatchdogLimits;
+use crate::window::WindowQuery;
+
+/// Settings of a single grower session. Validated once when the session is
+/// started and never changed afterwards.
+#[derive(Debug, Clone)]
+pub struct GrowerConfig {
+    pub goals: Goals,
+    pub num_objects: usize,
+    pub extra_delay: Duration,
+    pub input_method: InputMethod,
+    pub window: WindowQuery,
+    pub action_retries: usize,
+    /// go through the plots one by one and only do what each one needs
+    pub per_plot: bool,
+    pub crops: BTreeMap<String, CropPlan>,
+    pub replant_slots: Vec<ReplantSlot>,
+    pub replant_presses: usize,
+    pub empty_slot_policy: EmptySlotPolicy,
+    pub hotbar_slots: Vec<HotbarSlot>,
+    pub recovery_timeout: Duration,
+    pub recovery_auto_choice: RecoveryChoice,
+    /// None if the bot doesn't care about the user's input
+    pub idle_policy: Option<IdlePolicy>,
+    pub watchdog: WatchdogLimits,
+    /// pause while the game window is gone, minimized, blank or frozen
+    pub display_check: bool,
+    /// the sentinel region didn't change for that long, None to never check
+    pub frozen_after: Option<Duration>,
+}
+
+impl GrowerConfig {
+    /// Plan for the crop, tolerating a few misread characters in its name.
+    /// An empty name only ever matches exactly.
+    pub fn crop_plan(&self, crop: &str) -> Option<&CropPlan> {
+        if let Some(plan) = self.crops.get(crop) {
+            return Some(plan);
+        }
+        if crop.is_empty() {
+            return None;
+        }
+        self.crops
+            .iter()
+            .filter(|(name, _)| !name.is_empty())
+            .find(|(name, _)| garden::same_crop(name, crop))
+            .map(|(_, plan)| plan)
+    }
+
+    /// Seed counts of the replant slots that have them configured
+    pub fn known_seeds(&self) -> BTreeMap<u8, usize> {
+        self.replant_slots
+            .iter()
+            .filter_map(|replant_slot| Some((replant_slot.slot, replant_slot.seeds?)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    MissingSelectArea,
+    NoGoal,
+    InvalidStopTime,
+    InvalidGoalPlants,
+    InvalidGoalMinutes,
+    ZeroObjects,
+    InvalidSeedSlot(String),
+    InvalidReplantSlot(u8),
+    InvalidHotbarSlot(u8),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSelectArea => f.write_str("You need to select the area first"),
+            Self::NoGoal => {
+                f.write_str("You need to set at least one goal, e.g. the number of rounds")
+            }
+            Self::InvalidStopTime => f.write_str("Stop time must be in the HH:MM format"),
+            Self::InvalidGoalPlants => f.write_str("Harvested plants must be a number"),
+            Self::InvalidGoalMinutes => f.write_str("Run time must be a number of minutes"),
+            Self::ZeroObjects => f.write_str("You need to set the number of growing objects"),
+            Self::InvalidSeedSlot(crop) => {
+                write!(f, "Seed slot of {crop} must be between 1 and 10")
+            }
+            Self::InvalidReplantSlot(slot) => {
+                write!(f, "Replant slot {slot} must be between 1 and 10")
+            }
+            Self::InvalidHotbarSlot(slot) => {
+                write!(f, "Hotbar slot {slot} must be between 1 and 10")
+            }
+        }
+    }
+}
+
+impl TryFrom<&Profile> for GrowerConfig {
+    type Error = ConfigError;
+
+    fn try_from(profile: &Profile) -> Result<Self, Self::Error> {
+        if profile.select_region.is_none() {
+            return Err(ConfigError::MissingSelectArea);
+        }
+        let stop_at = match profile.stop_at.trim() {
+            "" => None,
+            text => Some(goals::parse_time(text).ok_or(ConfigError::InvalidStopTime)?),
+        };
+        let goals = Goals {
+            rounds: (profile.num_rounds > 0).then_some(profile.num_rounds),
+            plants: (profile.goal_plants > 0).then_some(profile.goal_plants),
+            duration: (profile.goal_minutes > 0)
+                .then(|| Duration::from_secs(profile.goal_minutes as u64 * 60)),
+            stop_at,
+            until_out_of_stock: profile.until_out_of_stock,
+        };
+        if goals.is_empty() {
+            return Err(ConfigError::NoGoal);
+        }
+        if profile.num_objects == 0 {
+            return Err(ConfigError::ZeroObjects);
+        }
+        for (crop, plan) in &profile.crops {
+            if plan.seed_slot.is_some_and(|slot| !(1..=10).contains(&slot)) {
+                return Err(ConfigError::InvalidSeedSlot(crop.clone()));
+            }
+        }
+        for replant_slot in &profile.replant_slots {
+            if !(1..=10).contains(&replant_slot.slot) {
+                return Err(ConfigError::InvalidReplantSlot(replant_slot.slot));
+            }
+        }
+        for hotbar_slot in &profile.hotbar_slots {
+            if !(1..=hotbar::NUM_SLOTS).contains(&hotbar_slot.slot) {
+                return Err(ConfigError::InvalidHotbarSlot(hotbar_slot.slot));
+            }
+        }
+
+        Ok(Self {
+            goals,
+            num_objects: profile.num_objects,
+            extra_delay: Duration::from_secs(profile.extra_delay_secs as u64),
+            input_method: profile.input_method,
+            window: profile.window_query(),
+            action_retries: profile.action_retries,
+            per_plot: profile.per_plot,
+            crops: profile.crops.clone(),
+            replant_slots: profile.replant_slots.clone(),
+            replant_presses: profile.replant_presses,
+            empty_slot_policy: profile.empty_slot_policy,
+            hotbar_slots: profile.hotbar_slots.clone(),
+            recovery_timeout: Duration::from_secs(profile.recovery_timeout_secs),
+            recovery_auto_choice: profile.recovery_auto_choice,
+            idle_policy: (profile.user_active_secs > 0).then(|| IdlePolicy {
+                active_window: Duration::from_secs(profile.user_active_secs),
+                quiet_period: Duration::from_secs(profile.user_idle_secs),
+            }),
+            watchdog: WatchdogLimits {
+                keypresses_per_minute: (profile.max_keypresses_per_minute > 0)
+                    .then_some(profile.max_keypresses_per_minute),
+                unknown_reads: (profile.max_unknown_reads > 0).then_some(profile.max_unknown_reads),
+                rounds_without_change: (profile.max_rounds_without_change > 0)
+                    .then_some(profile.max_rounds_without_change),
+                action_state_time: (profile.max_action_state_secs > 0)
+                    .then(|| Duration::from_secs(profile.max_action_state_secs)),
+            },
+            display_check: profile.display_check,
+            frozen_after: (profile.frozen_secs > 0)
+                .then(|| Duration::from_secs(profile.frozen_secs)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{Anchor, WindowRegion};
+
+    fn profile() -> Profile {
+        Profile {
+            select_region: Some(WindowRegion {
+                anchor: Anchor::Offset,
+                x: 0.0,
+                y: 0.0,
+                w: 100.0,
+                h: 20.0,
+            }),
+            num_objects: 1,
+            num_rounds: 1,
+            ..Profile::default()
+        }
+    }
+
+    fn with_crops(crops: &[&str]) -> GrowerConfig {
+        let profile = Profile {
+            crops: crops
+                .iter()
+                .map(|crop| (crop.to_string(), CropPlan::default()))
+                .collect(),
+            ..profile()
+        };
+        GrowerConfig::try_from(&profile).unwrap()
+    }
+
+    #[test]
+    fn crop_plan_tolerates_misread_names() {
+        let config = with_crops(&["Cotton", "Flax"]);
+        assert!(config.crop_plan("Cotton").is_some());
+        assert!(config.crop_plan("Cottn").is_some());
+        assert!(config.crop_plan("Fiax").is_some());
+        assert!(config.crop_plan("Barley").is_none());
+    }
+
+    #[test]
+    fn empty_crop_names_only_match_exactly() {
+        let config = with_crops(&["A", "Oat"]);
+        assert!(config.crop_plan("").is_none());
+        assert!(config.crop_plan("B").is_some());
+
+        let config = with_crops(&[""]);
+        assert!(config.crop_plan("").is_some());
+        assert!(config.crop_plan("A").is_none());
+    }
+
+    #[test]
+    fn zero_frozen_secs_turns_the_frozen_check_off() {
+        let default = GrowerConfig::try_from(&profile()).unwrap();
+        assert_eq!(default.frozen_after, Some(Duration::from_secs(20)));
+
+        let off = Profile {
+            frozen_secs: 0,
+            ..profile()
+        };
+        assert_eq!(GrowerConfig::try_from(&off).unwrap().frozen_after, None);
+    }
+}
@@ -1,3 +1,4 @@
+use super::image::{Orientation, PixelFormat};
 use super::screenshot::{Rectangle, Screenshot};
 use glium::{
     self,
@@ -138,12 +139,12 @@ impl Cropper {
             snap_tex: SrgbTexture2d::with_mipmaps(
                 &self.display,
                 RawImage2d::from_raw_rgb(
-                    // BGR => RGB
-                    snap.bgra
-                        .chunks_exact(3)
-                        .flat_map(|p| [p[2], p[1], p[0]])
-                        .collect(),
-                    (snap.bounds.w as u32, snap.bounds.h as u32),
+                    // textures are bottom-up too
+                    snap.image
+                        .view()
+                        .convert(PixelFormat::Rgb, Orientation::BottomUp)
+                        .into_data(),
+                    (snap.image.width(), snap.image.height()),
                 ),
                 MipmapsOption::NoMipmap,
             )
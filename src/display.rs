@@ -0,0 +1,224 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::Hasher;
+use std::time::{Duration, Instant};
+
+use crate::image::ImageView;
+
+/// A region is blank if no color channel varies more than that
+const BLANK_SPREAD: u8 = 8;
+
+/// Why the game can't be seen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplayProblem {
+    Gone,
+    Minimized,
+    Blank,
+    Frozen,
+}
+
+impl fmt::Display for DisplayProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gone => f.write_str("Game window is gone"),
+            Self::Minimized => f.write_str("Game window is minimized"),
+            Self::Blank => f.write_str("Game screen is blank"),
+            Self::Frozen => f.write_str("Game screen is frozen"),
+        }
+    }
+}
+
+/// Fingerprint of the sentinel region in a single screenshot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    hash: u64,
+    blank: bool,
+}
+
+impl Frame {
+    pub fn of(image: &ImageView) -> Self {
+        let mut hasher = DefaultHasher::new();
+        let mut min = [u8::MAX; 4];
+        let mut max = [u8::MIN; 4];
+        let channels = image.format().bytes_per_pixel();
+        for row in image.rows() {
+            hasher.write(row);
+            for pixel in row.chunks_exact(channels) {
+                for c in 0..channels {
+                    min[c] = min[c].min(pixel[c]);
+                    max[c] = max[c].max(pixel[c]);
+                }
+            }
+        }
+        Self {
+            hash: hasher.finish(),
+            blank: (0..channels).all(|c| max[c].saturating_sub(min[c]) <= BLANK_SPREAD),
+        }
+    }
+}
+
+/// Tells whether the game is still drawing anything, based on the recent
+/// frames of a sentinel region that normally never stays still
+#[derive(Debug)]
+pub struct DisplayWatch {
+    /// None to never consider the game frozen
+    frozen_after: Option<Duration>,
+    last: Option<Frame>,
+    changed_at: Instant,
+}
+
+impl DisplayWatch {
+    pub fn new(frozen_after: Option<Duration>, now: Instant) -> Self {
+        Self {
+            frozen_after,
+            last: None,
+            changed_at: now,
+        }
+    }
+
+    pub fn frame(&mut self, now: Instant, frame: Frame) {
+        if self.last.is_none_or(|last| last.hash != frame.hash) {
+            self.changed_at = now;
+        }
+        self.last = Some(frame);
+    }
+
+    /// Problem with the game window itself, or the recent frames. `minimized`
+    /// is None if the window is gone.
+    pub fn problem(&self, now: Instant, minimized: Option<bool>) -> Option<DisplayProblem> {
+        let Some(minimized) = minimized else {
+            return Some(DisplayProblem::Gone);
+        };
+        if minimized {
+            return Some(DisplayProblem::Minimized);
+        }
+        let last = self.last?;
+        if last.blank {
+            return Some(DisplayProblem::Blank);
+        }
+        if self
+            .frozen_after
+            .is_some_and(|after| now.saturating_duration_since(self.changed_at) >= after)
+        {
+            return Some(DisplayProblem::Frozen);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, FakeClock};
+    use crate::image::{Image, Orientation, PixelFormat};
+
+    const FROZEN_AFTER: Duration = Duration::from_secs(30);
+
+    /// Frame of a single row of RGB pixels
+    fn frame(pixels: &[[u8; 3]]) -> Frame {
+        let data = pixels.concat();
+        let image = Image::from_raw(
+            pixels.len() as u32,
+            1,
+            data.len(),
+            PixelFormat::Rgb,
+            Orientation::TopDown,
+            data,
+        )
+        .unwrap();
+        Frame::of(&image.view())
+    }
+
+    fn water(shade: u8) -> Frame {
+        frame(&[[0, 40, 200], [20, 80, shade], [10, 60, 180]])
+    }
+
+    #[test]
+    fn window_problems_come_first() {
+        let clock = FakeClock::new();
+        let mut watch = DisplayWatch::new(Some(FROZEN_AFTER), clock.now());
+        assert_eq!(watch.problem(clock.now(), Some(false)), None);
+        watch.frame(clock.now(), frame(&[[0, 0, 0]; 3]));
+        assert_eq!(watch.problem(clock.now(), None), Some(DisplayProblem::Gone));
+        assert_eq!(
+            watch.problem(clock.now(), Some(true)),
+            Some(DisplayProblem::Minimized)
+        );
+    }
+
+    #[test]
+    fn uniform_frames_are_blank() {
+        let clock = FakeClock::new();
+        let mut watch = DisplayWatch::new(Some(FROZEN_AFTER), clock.now());
+        watch.frame(clock.now(), frame(&[[0, 0, 0]; 3]));
+        assert_eq!(
+            watch.problem(clock.now(), Some(false)),
+            Some(DisplayProblem::Blank)
+        );
+        // a bit of noise on a loading screen
+        watch.frame(
+            clock.now(),
+            frame(&[[30, 30, 30], [36, 34, 30], [38, 30, 32]]),
+        );
+        assert_eq!(
+            watch.problem(clock.now(), Some(false)),
+            Some(DisplayProblem::Blank)
+        );
+        watch.frame(clock.now(), water(220));
+        assert_eq!(watch.problem(clock.now(), Some(false)), None);
+    }
+
+    #[test]
+    fn unchanged_frames_are_frozen_after_a_while() {
+        let clock = FakeClock::new();
+        let mut watch = DisplayWatch::new(Some(FROZEN_AFTER), clock.now());
+        watch.frame(clock.now(), water(220));
+        clock.advance(FROZEN_AFTER - Duration::from_secs(1));
+        watch.frame(clock.now(), water(220));
+        assert_eq!(watch.problem(clock.now(), Some(false)), None);
+
+        clock.advance(Duration::from_secs(1));
+        watch.frame(clock.now(), water(220));
+        assert_eq!(
+            watch.problem(clock.now(), Some(false)),
+            Some(DisplayProblem::Frozen)
+        );
+    }
+
+    #[test]
+    fn a_changed_frame_unfreezes() {
+        let clock = FakeClock::new();
+        let mut watch = DisplayWatch::new(Some(FROZEN_AFTER), clock.now());
+        watch.frame(clock.now(), water(220));
+        clock.advance(FROZEN_AFTER);
+        assert_eq!(
+            watch.problem(clock.now(), Some(false)),
+            Some(DisplayProblem::Frozen)
+        );
+
+        watch.frame(clock.now(), water(230));
+        assert_eq!(watch.problem(clock.now(), Some(false)), None);
+        // and the time starts over from the change
+        clock.advance(FROZEN_AFTER - Duration::from_secs(1));
+        watch.frame(clock.now(), water(230));
+        assert_eq!(watch.problem(clock.now(), Some(false)), None);
+    }
+
+    #[test]
+    fn never_frozen_without_a_limit() {
+        // frozen_secs = 0 in the profile
+        let clock = FakeClock::new();
+        let mut watch = DisplayWatch::new(None, clock.now());
+        watch.frame(clock.now(), water(220));
+        clock.advance(Duration::from_secs(24 * 3600));
+        watch.frame(clock.now(), water(220));
+        assert_eq!(watch.problem(clock.now(), Some(false)), None);
+
+        // blank frames are still caught
+        watch.frame(clock.now(), frame(&[[0, 0, 0]; 3]));
+        assert_eq!(
+            watch.problem(clock.now(), Some(false)),
+            Some(DisplayProblem::Blank)
+        );
+    }
+}
@@ -0,0 +1,405 @@
+use std::time::{Duration, Instant};
+
+use levenshtein::levenshtein;
+
+use crate::grower::{CurrentlySelected, Selection};
+use crate::log::log;
+use crate::stats::GrowthStats;
+
+/// Last known state of a single growing object
+#[derive(Debug, Clone)]
+pub struct Plot {
+    pub crop: String,
+    pub state: CurrentlySelected,
+    /// when the plot entered its current state, None if it was already in it
+    /// when we first saw it
+    pub since: Option<Instant>,
+    /// when the plot was last read, None if never
+    pub last_seen: Option<Instant>,
+    /// what the plot needed before it started growing (None once planted),
+    /// if we saw it
+    pub grew_from: Option<CurrentlySelected>,
+}
+
+impl Plot {
+    /// When the plot should need something next, based on how long the crop
+    /// took to grow before
+    pub fn predicted_action(&self, stats: &GrowthStats) -> Option<Instant> {
+        if self.state != CurrentlySelected::Growing {
+            return None;
+        }
+        let after = stats.crop(&self.crop)?.next_action(self.grew_from?)?;
+        Some(self.since? + after)
+    }
+
+    /// Whether the plot is empty or about to be. A ripe plot that's something
+    /// else on the next read was harvested in between.
+    fn empty(&self) -> bool {
+        matches!(
+            self.state,
+            CurrentlySelected::None | CurrentlySelected::Ripe
+        )
+    }
+}
+
+/// What a read of the selected plot showed happened to it
+#[derive(Debug)]
+pub enum Change {
+    StageDone(StageDone),
+    /// the empty plot was planted with the crop
+    Planted(String),
+}
+
+/// A plot was seen finishing a growth stage
+#[derive(Debug)]
+pub struct StageDone {
+    pub crop: String,
+    /// what the plot needed before it was growing
+    pub from: CurrentlySelected,
+    pub to: CurrentlySelected,
+    pub took: Duration,
+}
+
+/// All plots of the garden, in the order the game cycles through them with
+/// the next target key
+#[derive(Debug, Default)]
+pub struct Garden {
+    plots: Vec<Plot>,
+    /// index of the currently selected plot
+    cursor: usize,
+}
+
+impl Garden {
+    /// Start cycling from the currently selected plot. The plots are
+    /// forgotten only if their number changed.
+    pub fn start_cycle(&mut self, num_plots: usize) {
+        if self.plots.len() != num_plots {
+            self.plots = vec![
+                Plot {
+                    crop: String::new(),
+                    state: CurrentlySelected::None,
+                    since: None,
+                    last_seen: None,
+                    grew_from: None,
+                };
+                num_plots
+            ];
+        }
+        self.cursor = 0;
+    }
+
+    /// The plots were counted by reading each of them once, starting from the
+    /// selected one
+    pub fn counted(&mut self, at: Instant, selections: &[Selection]) {
+        self.plots = selections
+            .iter()
+            .map(|sel| Plot {
+                crop: sel.crop.clone(),
+                state: sel.state,
+                since: None,
+                last_seen: Some(at),
+                grew_from: None,
+            })
+            .collect();
+        self.cursor = 0;
+    }
+
+    pub fn plots(&self) -> &[Plot] {
+        &self.plots
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// The next plot was targeted
+    pub fn next(&mut self) {
+        if !self.plots.is_empty() {
+            self.cursor = (self.cursor + 1) % self.plots.len();
+        }
+    }
+
+    /// The selected plot was read from the screen. Returns the growth stage
+    /// the plot just finished if we saw it start, or whether it was planted.
+    pub fn observe(&mut self, at: Instant, sel: &Selection) -> Option<Change> {
+        // nothing targeted, e.g. the plot was just harvested
+        if self.plots.is_empty() || sel.state == CurrentlySelected::None {
+            return None;
+        }
+
+        if let Some(idx) = self.find_plot(&sel.crop) {
+            log!(
+                "Garden out of sync, plot {} is now {}",
+                self.cursor + 1,
+                idx + 1
+            );
+            self.cursor = idx;
+        }
+
+        let plot = &mut self.plots[self.cursor];
+        let crop_changed = !same_crop(&plot.crop, &sel.crop);
+        let was_empty = plot.empty();
+        let planted =
+            plot.last_seen.is_some() && was_empty && (crop_changed || plot.state != sel.state);
+        let mut change = None;
+        if crop_changed {
+            // replanted with something else, or we never saw it before
+            plot.since = plot.last_seen.map(|_| at);
+            plot.grew_from = plot.last_seen.map(|_| CurrentlySelected::None);
+        } else if plot.state != sel.state {
+            if let (CurrentlySelected::Growing, Some(since), Some(from)) =
+                (plot.state, plot.since, plot.grew_from)
+            {
+                change = Some(Change::StageDone(StageDone {
+                    crop: sel.crop.clone(),
+                    from,
+                    to: sel.state,
+                    took: at - since,
+                }));
+            }
+            plot.since = plot.last_seen.map(|_| at);
+            let from = if was_empty {
+                CurrentlySelected::None
+            } else {
+                plot.state
+            };
+            plot.grew_from = plot.last_seen.map(|_| from);
+        }
+        if planted {
+            change = Some(Change::Planted(sel.crop.clone()));
+        }
+        plot.crop.clone_from(&sel.crop);
+        plot.state = sel.state;
+        plot.last_seen = Some(at);
+        change
+    }
+
+    /// Number of plots seen empty or about to be harvested
+    pub fn empty_plots(&self) -> usize {
+        self.plots
+            .iter()
+            .filter(|plot| plot.last_seen.is_some() && plot.empty())
+            .count()
+    }
+
+    /// The earliest time any plot should need something
+    pub fn next_action(&self, stats: &GrowthStats) -> Option<Instant> {
+        self.plots
+            .iter()
+            .filter_map(|plot| plot.predicted_action(stats))
+            .min()
+    }
+
+    /// The selected plot was harvested and is empty now
+    pub fn harvested(&mut self, at: Instant) {
+        if let Some(plot) = self.plots.get_mut(self.cursor) {
+            plot.state = CurrentlySelected::None;
+            plot.since = Some(at);
+            plot.last_seen = Some(at);
+        }
+    }
+
+    /// If the crop doesn't match the plot under the cursor, we must have lost
+    /// track of the cycle. Look for the closest plot with that crop.
+    fn find_plot(&self, crop: &str) -> Option<usize> {
+        let cur = &self.plots[self.cursor];
+        // the plot is either new to us or could have been replanted with something else
+        if cur.last_seen.is_none() || cur.state == CurrentlySelected::None {
+            return None;
+        }
+        if same_crop(&cur.crop, crop) {
+            return None;
+        }
+
+        (1..self.plots.len())
+            .map(|i| (self.cursor + i) % self.plots.len())
+            .find(|&i| self.plots[i].last_seen.is_some() && same_crop(&self.plots[i].crop, crop))
+    }
+}
+
+/// How many full cycles the readings must go through before we trust them
+const CYCLE_REPEATS: usize = 3;
+
+/// What the readings tell about the number of plots
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cycle {
+    /// not enough readings yet
+    Unknown,
+    /// the plots repeat after that many
+    Length(usize),
+    /// the readings fit both numbers of plots. Crop names are matched
+    /// loosely, so more readings may still rule one of them out.
+    Ambiguous(usize, usize),
+}
+
+/// Number of plots the readings cycle through, once they repeated fully
+/// [`CYCLE_REPEATS`] times. The readings are taken one after another with
+/// the next target key.
+pub fn cycle_length(readings: &[Selection]) -> Cycle {
+    let mut lengths = (1..=readings.len() / CYCLE_REPEATS).filter(|&len| {
+        (len..readings.len()).all(|i| {
+            let (a, b) = (&readings[i - len], &readings[i]);
+            a.state == b.state && same_crop(&a.crop, &b.crop)
+        })
+    });
+    let Some(len) = lengths.next() else {
+        return Cycle::Unknown;
+    };
+    // multiples are the same cycle going around more than once
+    match lengths.find(|other| other % len != 0) {
+        Some(other) => Cycle::Ambiguous(len, other),
+        None => Cycle::Length(len),
+    }
+}
+
+/// Crop names come from OCR, so allow a misread character in short names
+/// and about one in five characters in longer ones
+pub fn same_crop(a: &str, b: &str) -> bool {
+    let len = a.chars().count().max(b.chars().count());
+    levenshtein(a, b) <= (len / 5).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CurrentlySelected as C;
+
+    fn readings(crops: &str) -> Vec<Selection> {
+        crops
+            .split(' ')
+            .map(|crop| Selection {
+                state: CurrentlySelected::Growing,
+                crop: crop.to_string(),
+            })
+            .collect()
+    }
+
+    fn sel(state: CurrentlySelected) -> Selection {
+        Selection {
+            state,
+            crop: "Cotton".to_string(),
+        }
+    }
+
+    #[test]
+    fn predicts_from_what_the_plot_needed_before() {
+        let mut stats = GrowthStats::default();
+        stats.record("Cotton", C::None, C::Thisty, Duration::from_secs(60));
+        stats.record("Cotton", C::Thisty, C::Ripe, Duration::from_secs(600));
+
+        let start = Instant::now();
+        let mut garden = Garden::default();
+        garden.counted(start, &[sel(C::Thisty)]);
+        // we don't know how long it was thirsty
+        assert_eq!(garden.next_action(&stats), None);
+
+        let watered = start + Duration::from_secs(5);
+        garden.observe(watered, &sel(C::Growing));
+        assert_eq!(
+            garden.next_action(&stats),
+            Some(watered + Duration::from_secs(600))
+        );
+
+        let ripe = watered + Duration::from_secs(700);
+        let Some(Change::StageDone(done)) = garden.observe(ripe, &sel(C::Ripe)) else {
+            panic!("the stage wasn't done");
+        };
+        assert_eq!((done.from, done.to), (C::Thisty, C::Ripe));
+        assert_eq!(done.took, Duration::from_secs(700));
+
+        let replanted = ripe + Duration::from_secs(5);
+        garden.harvested(replanted);
+        assert!(matches!(
+            garden.observe(replanted, &sel(C::Growing)),
+            Some(Change::Planted(_))
+        ));
+        assert_eq!(
+            garden.next_action(&stats),
+            Some(replanted + Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn harvested_ripe_plot_growing_again_was_planted() {
+        let start = Instant::now();
+        let mut garden = Garden::default();
+        garden.counted(start, &[sel(C::Ripe), sel(C::Growing)]);
+        assert_eq!(garden.empty_plots(), 1);
+
+        // harvested and replanted between two reads
+        let later = start + Duration::from_secs(5);
+        assert!(matches!(
+            garden.observe(later, &sel(C::Growing)),
+            Some(Change::Planted(_))
+        ));
+        assert_eq!(garden.plots()[0].grew_from, Some(C::None));
+        assert_eq!(garden.empty_plots(), 0);
+
+        garden.next();
+        assert!(garden.observe(later, &sel(C::Growing)).is_none());
+    }
+
+    #[test]
+    fn counts_once_the_plots_repeated_three_times() {
+        let plots = "Cotton Flax Barley";
+        assert_eq!(
+            cycle_length(&readings(&[plots; 2].join(" "))),
+            Cycle::Unknown
+        );
+        assert_eq!(
+            cycle_length(&readings(&[plots; 3].join(" "))),
+            Cycle::Length(3)
+        );
+    }
+
+    #[test]
+    fn a_single_repeat_is_not_a_cycle() {
+        let garden = readings(&["Cotton Flax Cotton Flax Barley"; 3].join(" "));
+        for len in 1..garden.len() {
+            assert_eq!(cycle_length(&garden[..len]), Cycle::Unknown, "{len}");
+        }
+        assert_eq!(cycle_length(&garden), Cycle::Length(5));
+    }
+
+    #[test]
+    fn two_same_plots_in_a_row_are_not_a_cycle() {
+        let garden = readings(&["Cotton Cotton Flax"; 3].join(" "));
+        assert_eq!(cycle_length(&garden[..2]), Cycle::Unknown);
+        assert_eq!(cycle_length(&garden), Cycle::Length(3));
+    }
+
+    #[test]
+    fn plots_that_look_alike_repeat_after_one() {
+        assert_eq!(
+            cycle_length(&readings("Cotton Cotton Cotton")),
+            Cycle::Length(1)
+        );
+    }
+
+    #[test]
+    fn misread_names_can_fit_two_cycles() {
+        // each x is a misread character
+        let garden = readings(
+            "Watermelon Watermelon Watermelon Watermelon Watermelon Watermelonx \
+             Watermelonx Watermelon Watermelonxxx",
+        );
+        assert_eq!(cycle_length(&garden), Cycle::Ambiguous(2, 3));
+    }
+
+    #[test]
+    fn short_crop_names_allow_one_misread_character() {
+        assert!(same_crop("Flax", "Flax"));
+        assert!(same_crop("Flax", "Fiax"));
+        assert!(!same_crop("Flax", "Fiox"));
+        assert!(!same_crop("Rye", "Oat"));
+        assert!(!same_crop("Pea", ""));
+    }
+
+    #[test]
+    fn longer_crop_names_allow_more_misread_characters() {
+        assert!(same_crop("Cotton", "Cottn"));
+        assert!(!same_crop("Cotton", "Coton1"));
+        assert!(same_crop("Watermelon", "Watennelon"));
+        assert!(!same_crop("Watermelon", "Wafennelon"));
+    }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+
+use crate::screenshot::Rectangle;
+
+/// How a region follows the game window when it's moved or resized
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Anchor {
+    /// pixels from the top-left corner of the client area
+    #[default]
+    Offset,
+    /// fractions of the client area size
+    Fraction,
+}
+
+/// Region relative to the game window's client area
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowRegion {
+    pub anchor: Anchor,
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl WindowRegion {
+    /// Region of the `rect` picked on the screen, `client` being the client
+    /// area of the game window at that time
+    pub fn from_screen(rect: Rectangle<f64>, client: Rectangle<f64>, anchor: Anchor) -> Self {
+        let (x, y) = (rect.x - client.x, rect.y - client.y);
+        match anchor {
+            Anchor::Offset => Self {
+                anchor,
+                x,
+                y,
+                w: rect.w,
+                h: rect.h,
+            },
+            Anchor::Fraction => Self {
+                anchor,
+                x: x / client.w.max(1.0),
+                y: y / client.h.max(1.0),
+                w: rect.w / client.w.max(1.0),
+                h: rect.h / client.h.max(1.0),
+            },
+        }
+    }
+
+    /// Where the region is on the screen with the given client area
+    pub fn to_screen(self, client: Rectangle<f64>) -> Rectangle<f64> {
+        match self.anchor {
+            Anchor::Offset => Rectangle {
+                x: client.x + self.x,
+                y: client.y + self.y,
+                w: self.w,
+                h: self.h,
+            },
+            Anchor::Fraction => Rectangle {
+                x: client.x + self.x * client.w,
+                y: client.y + self.y * client.h,
+                w: self.w * client.w,
+                h: self.h * client.h,
+            },
+        }
+    }
+}
+
+/// Move a rectangle from the virtual screen coordinates, which can be
+/// negative with monitors left or above the primary one, to the coordinates
+/// of a screenshot of the whole `screen`
+pub fn screen_to_image(rect: Rectangle<i32>, screen: Rectangle<i32>) -> Rectangle<f64> {
+    Rectangle {
+        x: (rect.x - screen.x) as f64,
+        y: (rect.y - screen.y) as f64,
+        w: rect.w as f64,
+        h: rect.h as f64,
+    }
+}
+
+/// Whole pixels of the part of `rect` inside `bounds`, None if it's all
+/// outside
+pub fn clip(rect: Rectangle<f64>, bounds: Rectangle<u32>) -> Option<Rectangle<u32>> {
+    let (left, top) = (bounds.x as f64, bounds.y as f64);
+    let (right, bottom) = (left + bounds.w as f64, top + bounds.h as f64);
+    let x0 = rect.x.clamp(left, right) as u32;
+    let y0 = rect.y.clamp(top, bottom) as u32;
+    let x1 = (rect.x + rect.w).clamp(left, right) as u32;
+    let y1 = (rect.y + rect.h).clamp(top, bottom) as u32;
+    if x1 <= x0 || y1 <= y0 {
+        return None;
+    }
+    Some(Rectangle {
+        x: x0,
+        y: y0,
+        w: x1 - x0,
+        h: y1 - y0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect<T>(x: T, y: T, w: T, h: T) -> Rectangle<T> {
+        Rectangle { x, y, w, h }
+    }
+
+    const CLIENT: Rectangle<f64> = Rectangle {
+        x: 100.0,
+        y: 50.0,
+        w: 800.0,
+        h: 600.0,
+    };
+
+    #[test]
+    fn round_trip_keeps_the_picked_rect() {
+        let picked = rect(300.0, 350.0, 200.0, 30.0);
+        for anchor in [Anchor::Offset, Anchor::Fraction] {
+            let region = WindowRegion::from_screen(picked, CLIENT, anchor);
+            assert_eq!(region.anchor, anchor);
+            assert_eq!(region.to_screen(CLIENT), picked, "{anchor:?}");
+        }
+    }
+
+    #[test]
+    fn offset_region_moves_with_the_window_and_keeps_its_size() {
+        let region =
+            WindowRegion::from_screen(rect(300.0, 350.0, 200.0, 30.0), CLIENT, Anchor::Offset);
+        let moved_and_resized = rect(-500.0, 20.0, 1600.0, 1200.0);
+        assert_eq!(
+            region.to_screen(moved_and_resized),
+            rect(-300.0, 320.0, 200.0, 30.0)
+        );
+    }
+
+    #[test]
+    fn fraction_region_scales_with_the_window() {
+        let region =
+            WindowRegion::from_screen(rect(300.0, 350.0, 200.0, 30.0), CLIENT, Anchor::Fraction);
+        assert_eq!((region.x, region.y), (0.25, 0.5));
+        let doubled = rect(0.0, 0.0, 1600.0, 1200.0);
+        assert_eq!(region.to_screen(doubled), rect(400.0, 600.0, 400.0, 60.0));
+    }
+
+    #[test]
+    fn fraction_of_an_empty_client_area_stays_finite() {
+        let empty = rect(0.0, 0.0, 0.0, 0.0);
+        let region = WindowRegion::from_screen(rect(10.0, 10.0, 5.0, 5.0), empty, Anchor::Fraction);
+        assert!([region.x, region.y, region.w, region.h]
+            .iter()
+            .all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn screen_to_image_handles_monitors_left_of_the_primary() {
+        let screen = rect(-1920, 0, 3840, 1080);
+        assert_eq!(
+            screen_to_image(rect(-1900, 10, 300, 200), screen),
+            rect(20.0, 10.0, 300.0, 200.0)
+        );
+    }
+
+    #[test]
+    fn clip_keeps_what_is_inside() {
+        let bounds = rect(0, 0, 1920, 1080);
+        assert_eq!(
+            clip(rect(10.5, 20.0, 100.0, 50.0), bounds),
+            Some(rect(10, 20, 100, 50))
+        );
+    }
+
+    #[test]
+    fn clip_cuts_off_what_is_outside() {
+        let bounds = rect(0, 0, 1920, 1080);
+        assert_eq!(
+            clip(rect(-50.0, -10.0, 100.0, 40.0), bounds),
+            Some(rect(0, 0, 50, 30))
+        );
+        assert_eq!(
+            clip(rect(1900.0, 1070.0, 100.0, 40.0), bounds),
+            Some(rect(1900, 1070, 20, 10))
+        );
+    }
+
+    #[test]
+    fn clip_of_a_rect_all_outside_is_none() {
+        let bounds = rect(0, 0, 1920, 1080);
+        assert_eq!(clip(rect(-200.0, 0.0, 100.0, 40.0), bounds), None);
+        assert_eq!(clip(rect(0.0, 1080.0, 100.0, 40.0), bounds), None);
+        assert_eq!(clip(rect(10.0, 10.0, 0.0, 40.0), bounds), None);
+    }
+}
@@ -0,0 +1,246 @@
+use std::time::{Duration, Instant};
+
+#[cfg(windows)]
+use winapi::um::minwinbase::SYSTEMTIME;
+#[cfg(windows)]
+use winapi::um::sysinfoapi::GetLocalTime;
+
+use crate::state::StopReason;
+
+/// When a session is done. They can be combined, the first one reached ends
+/// the session.
+#[derive(Debug, Clone, Default)]
+pub struct Goals {
+    pub rounds: Option<usize>,
+    pub plants: Option<usize>,
+    pub duration: Option<Duration>,
+    /// local time of day as hours and minutes
+    pub stop_at: Option<(u32, u32)>,
+    /// run until any watched hotbar stack or seed slot is empty
+    pub until_out_of_stock: bool,
+}
+
+impl Goals {
+    pub fn is_empty(&self) -> bool {
+        self.rounds.is_none()
+            && self.plants.is_none()
+            && self.duration.is_none()
+            && self.stop_at.is_none()
+            && !self.until_out_of_stock
+    }
+
+    /// Start tracking a new session
+    pub fn start(&self, now: Instant) -> Progress {
+        let deadlines = [
+            self.duration,
+            self.stop_at
+                .map(|(hour, minute)| until_local_time(hour, minute)),
+        ];
+        Progress {
+            goals: self.clone(),
+            deadline: deadlines.into_iter().flatten().min().map(|dur| now + dur),
+            rounds: 0,
+            plants: 0,
+        }
+    }
+}
+
+/// What the session achieved so far
+#[derive(Debug, Clone)]
+pub struct Progress {
+    pub goals: Goals,
+    /// the earliest of the time goals
+    deadline: Option<Instant>,
+    pub rounds: usize,
+    pub plants: usize,
+}
+
+impl Progress {
+    /// Why the session is done, if it is
+    pub fn reached(&self, now: Instant) -> Option<StopReason> {
+        if self
+            .goals
+            .rounds
+            .is_some_and(|rounds| self.rounds >= rounds)
+        {
+            return Some(StopReason::FinishedAllRounds);
+        }
+        if self
+            .goals
+            .plants
+            .is_some_and(|plants| self.plants >= plants)
+        {
+            return Some(StopReason::HarvestedAllPlants);
+        }
+        if self.deadline.is_some_and(|deadline| now >= deadline) {
+            return Some(StopReason::TimeIsUp);
+        }
+        None
+    }
+
+    /// Remaining progress of every goal, e.g. "2/5 rounds, 13m left"
+    pub fn summary(&self, now: Instant) -> String {
+        let mut parts = Vec::new();
+        if let Some(rounds) = self.goals.rounds {
+            parts.push(format!("{}/{rounds} rounds", self.rounds));
+        }
+        if let Some(plants) = self.goals.plants {
+            parts.push(format!("{}/{plants} plants", self.plants));
+        }
+        if let Some(deadline) = self.deadline {
+            let left = deadline.saturating_duration_since(now);
+            parts.push(format!("{} left", crate::format_duration(left)));
+        }
+        if self.goals.until_out_of_stock {
+            parts.push("until out of stock".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+/// Parse a "HH:MM" time of day. The hour may have a single digit, the
+/// minutes always have two.
+pub fn parse_time(text: &str) -> Option<(u32, u32)> {
+    let (hour, minute) = text.trim().split_once(':')?;
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if !(1..=2).contains(&hour.len()) || minute.len() != 2 || !digits(hour) || !digits(minute) {
+        return None;
+    }
+    let (hour, minute) = (hour.parse().ok()?, minute.parse().ok()?);
+    (hour < 24 && minute < 60).then_some((hour, minute))
+}
+
+/// Time until the given local time of day, today or tomorrow
+fn until_local_time(hour: u32, minute: u32) -> Duration {
+    const DAY_SECS: u32 = 24 * 3600;
+    let now_secs = local_time_of_day();
+    let target_secs = hour * 3600 + minute * 60;
+    let secs = match (target_secs + DAY_SECS - now_secs) % DAY_SECS {
+        0 => DAY_SECS,
+        secs => secs,
+    };
+    Duration::from_secs(secs as u64)
+}
+
+/// Seconds since the local midnight
+#[cfg(windows)]
+fn local_time_of_day() -> u32 {
+    let mut now: SYSTEMTIME = unsafe { std::mem::zeroed() };
+    unsafe { GetLocalTime(&mut now) };
+    now.wHour as u32 * 3600 + now.wMinute as u32 * 60 + now.wSecond as u32
+}
+
+/// Seconds since midnight. The bot only runs on Windows, elsewhere this is
+/// just good enough for the tests, so it ignores the time zone.
+#[cfg(not(windows))]
+fn local_time_of_day() -> u32 {
+    let since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    (since_epoch.as_secs() % (24 * 3600)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_times_of_day() {
+        assert_eq!(parse_time("07:05"), Some((7, 5)));
+        assert_eq!(parse_time("7:05"), Some((7, 5)));
+        assert_eq!(parse_time(" 23:59 "), Some((23, 59)));
+        assert_eq!(parse_time("0:00"), Some((0, 0)));
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        for text in [
+            "", ":", "7", "7:", ":05", "24:00", "12:60", "7:5", "007:05", "+7:05", "7:-5", "ab:cd",
+            "7:05:00", "7.05",
+        ] {
+            assert_eq!(parse_time(text), None, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn rounds_goal_is_reached_after_the_last_round() {
+        let now = Instant::now();
+        let goals = Goals {
+            rounds: Some(2),
+            ..Goals::default()
+        };
+        let mut progress = goals.start(now);
+        progress.rounds = 1;
+        assert_eq!(progress.reached(now), None);
+        progress.rounds = 2;
+        assert_eq!(progress.reached(now), Some(StopReason::FinishedAllRounds));
+    }
+
+    #[test]
+    fn plants_goal_is_reached_after_the_last_harvest() {
+        let now = Instant::now();
+        let goals = Goals {
+            plants: Some(10),
+            ..Goals::default()
+        };
+        let mut progress = goals.start(now);
+        progress.plants = 9;
+        assert_eq!(progress.reached(now), None);
+        progress.plants = 12;
+        assert_eq!(progress.reached(now), Some(StopReason::HarvestedAllPlants));
+    }
+
+    #[test]
+    fn duration_goal_is_reached_once_the_time_is_up() {
+        let start = Instant::now();
+        let goals = Goals {
+            duration: Some(Duration::from_secs(600)),
+            ..Goals::default()
+        };
+        let progress = goals.start(start);
+        assert_eq!(progress.reached(start + Duration::from_secs(599)), None);
+        assert_eq!(
+            progress.reached(start + Duration::from_secs(600)),
+            Some(StopReason::TimeIsUp)
+        );
+    }
+
+    #[test]
+    fn out_of_stock_goal_is_left_to_the_grower() {
+        let now = Instant::now();
+        let goals = Goals {
+            until_out_of_stock: true,
+            ..Goals::default()
+        };
+        assert!(!goals.is_empty());
+        assert!(Goals::default().is_empty());
+        assert_eq!(
+            goals.start(now).reached(now + Duration::from_secs(3600)),
+            None
+        );
+    }
+
+    #[test]
+    fn summarizes_every_goal() {
+        let start = Instant::now();
+        let goals = Goals {
+            rounds: Some(5),
+            plants: Some(40),
+            duration: Some(Duration::from_secs(900)),
+            until_out_of_stock: true,
+            ..Goals::default()
+        };
+        let mut progress = goals.start(start);
+        progress.rounds = 2;
+        progress.plants = 13;
+        assert_eq!(
+            progress.summary(start + Duration::from_secs(120)),
+            "2/5 rounds, 13/40 plants, 13m 0s left, until out of stock"
+        );
+        assert_eq!(
+            progress.summary(start + Duration::from_secs(1000)),
+            "2/5 rounds, 13/40 plants, 0s left, until out of stock"
+        );
+        assert_eq!(Goals::default().start(start).summary(start), "");
+    }
+}
@@ -1,19 +1,37 @@
+use std::collections::{BTreeMap, VecDeque};
 use std::convert::TryFrom;
-use std::ffi::CString;
-use std::mem::MaybeUninit;
-use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
-use std::sync::mpsc::{Receiver, Sender};
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard, Weak};
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use levenshtein::levenshtein;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use winapi::shared::windef::HWND;
-use winapi::um::winuser::{
-    FindWindowA, GetForegroundWindow, INPUT_u, SendInput, SetForegroundWindow, INPUT,
-    INPUT_KEYBOARD, KEYEVENTF_KEYUP,
-};
+
+use crate::clock::Clock;
+use crate::config::GrowerConfig;
+use crate::display::{DisplayWatch, Frame};
+use crate::garden::{self, Change, Cycle, Garden};
+use crate::goals::Progress;
+use crate::hotbar::StockAction;
+use crate::idle::{IdleWatch, InputActivity};
+use crate::input::{InputMethod, KeyInput};
+use crate::log::{error, log};
+use crate::profile::{EmptySlotPolicy, RecoveryChoice};
+use crate::state::{GrowerEvent, GrowerState, StateMachine, StopReason};
+use crate::stats::GrowthStats;
+use crate::watchdog::{Watchdog, WatchdogTrip};
+use crate::window::{Desktop, WindowQuery, HWND};
+
+/// How long to wait for the screen reader to catch up after a keypress
+const FRESH_READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Counting gives up if the plots don't repeat by then
+const MAX_PLOTS: usize = 40;
+/// Readings to take before giving up on counting
+const MAX_COUNTING_READS: usize = MAX_PLOTS * 3;
+/// Pause reason while the user is busy with the keyboard or mouse
+const USER_ACTIVE: &str = "You are using the keyboard or mouse";
 
 #[derive(Debug, Clone, Copy, PartialEq, IntoPrimitive, TryFromPrimitive)]
 #[repr(u8)]
@@ -56,266 +74,1925 @@ impl TryFrom<&str> for CurrentlySelected {
     }
 }
 
+/// Everything recognized in the selected entity area
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub state: CurrentlySelected,
+    /// name of the crop following the state, e.g. "Cotton"
+    pub crop: String,
+}
+
+impl Default for Selection {
+    fn default() -> Self {
+        Self {
+            state: CurrentlySelected::None,
+            crop: String::new(),
+        }
+    }
+}
+
+impl From<&str> for Selection {
+    fn from(value: &str) -> Self {
+        let state = CurrentlySelected::try_from(value).unwrap();
+        if state == CurrentlySelected::None {
+            return Self::default();
+        }
+
+        // same as above, the state is the first word of at least 4 characters
+        let crop = value
+            .split_whitespace()
+            .skip_while(|word| word.len() < 4)
+            .skip(1)
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self { state, crop }
+    }
+}
+
 #[derive(Debug)]
 pub struct Grower {
     thread: Mutex<Option<GrowerThreadHandle>>,
-    tx: Mutex<Option<Sender<GrowerThreadKick>>>,
-    pub running: AtomicBool,
-    pub num_rounds: AtomicUsize,
-    pub num_objects: AtomicUsize,
-    pub extra_delay_secs: AtomicUsize,
-    pub cur_selected: AtomicU8,
-    pub status_str: Mutex<[String; 2]>,
+    tx: Mutex<Option<Sender<GrowerCommand>>>,
+    pub clock: Arc<dyn Clock>,
+    pub activity: Arc<dyn InputActivity>,
+    desktop: Arc<dyn Desktop>,
+    /// a start command was sent, but the thread didn't pick it up yet
+    starting: AtomicBool,
+    /// a pause command was sent, but the thread didn't pause yet
+    pausing: AtomicBool,
+    /// the thread waits for the screen to be read after a keypress
+    awaiting_read: AtomicBool,
+    pub machine: Mutex<StateMachine>,
+    pub garden: Mutex<Garden>,
+    /// shared by all growers
+    pub stats: Arc<Mutex<GrowthStats>>,
+    /// of the current or the last session
+    pub progress: Mutex<Option<Progress>>,
+    /// the user is asked how to go on
+    pub recovery: Mutex<Option<RecoveryPrompt>>,
+    /// result of the last successful plot counting, until it's taken
+    pub counted_plots: Mutex<Option<usize>>,
+    /// when this grower last pressed a key
+    last_input: Mutex<Option<Instant>>,
+}
+
+/// Question for the user after something unexpected happened mid-action
+#[derive(Debug, Clone)]
+pub struct RecoveryPrompt {
+    pub reason: StopReason,
+    /// the auto choice is made after that
+    pub deadline: Instant,
+    pub auto_choice: RecoveryChoice,
 }
 
+/// All growers ever created, so they can be shut down from the panic hook
+static GROWERS: Mutex<Vec<Weak<Grower>>> = Mutex::new(Vec::new());
+
 impl Grower {
-    pub fn new() -> Arc<Self> {
-        let (tx, rx): (Sender<GrowerThreadKick>, Receiver<GrowerThreadKick>) = mpsc::channel();
+    pub fn new(
+        clock: Arc<dyn Clock>,
+        activity: Arc<dyn InputActivity>,
+        desktop: Arc<dyn Desktop>,
+    ) -> Arc<Self> {
+        let (grower, rx) = Self::unstarted(clock, activity, desktop, GrowthStats::shared());
+        let thread = GrowerThread::spawn(grower.clone(), rx);
+        *grower.thread.lock().unwrap() = Some(thread);
+        lock_ignore_poison(&GROWERS).push(Arc::downgrade(&grower));
+        grower
+    }
+
+    /// Grower without a thread yet, which is to be fed from the returned
+    /// receiver
+    fn unstarted(
+        clock: Arc<dyn Clock>,
+        activity: Arc<dyn InputActivity>,
+        desktop: Arc<dyn Desktop>,
+        stats: Arc<Mutex<GrowthStats>>,
+    ) -> (Arc<Self>, Receiver<GrowerCommand>) {
+        let (tx, rx): (Sender<GrowerCommand>, Receiver<GrowerCommand>) = mpsc::channel();
 
         let grower = Arc::new(Self {
             thread: Mutex::new(None),
             tx: Mutex::new(Some(tx)),
-            running: AtomicBool::new(false),
-            num_rounds: AtomicUsize::new(0),
-            num_objects: AtomicUsize::new(0),
-            extra_delay_secs: AtomicUsize::new(0),
-            cur_selected: AtomicU8::new(CurrentlySelected::None.into()),
-            status_str: Mutex::new([String::with_capacity(256), String::with_capacity(256)]),
+            clock,
+            activity,
+            desktop,
+            starting: AtomicBool::new(false),
+            pausing: AtomicBool::new(false),
+            awaiting_read: AtomicBool::new(false),
+            machine: Mutex::new(StateMachine::new()),
+            garden: Mutex::new(Garden::default()),
+            stats,
+            progress: Mutex::new(None),
+            recovery: Mutex::new(None),
+            counted_plots: Mutex::new(None),
+            last_input: Mutex::new(None),
         });
+        (grower, rx)
+    }
 
-        let thread = GrowerThread::spawn(grower.clone(), rx);
-        *grower.thread.lock().unwrap() = Some(thread);
-        grower
+    /// Whether a session is in progress or about to start
+    pub fn is_running(&self) -> bool {
+        self.starting.load(Ordering::Relaxed) || self.machine.lock().unwrap().state().is_running()
+    }
+
+    /// Whether the session is paused or about to be, by the user or on its own
+    pub fn is_paused(&self) -> bool {
+        self.pausing.load(Ordering::Relaxed) || self.machine.lock().unwrap().state().is_paused()
+    }
+
+    /// Whether the screen should be read as soon as possible
+    pub fn awaiting_read(&self) -> bool {
+        self.awaiting_read.load(Ordering::Relaxed)
+    }
+
+    pub fn start(&self, config: GrowerConfig) {
+        self.starting.store(true, Ordering::Relaxed);
+        self.send(GrowerCommand::Start(Box::new(config)));
     }
 
-    pub fn start(&self) {
-        // first break any currently running session
-        self.running.store(false, Ordering::Relaxed);
-        // then start a new one
-        self.kick();
+    /// Cycle through all plots in the game to count them
+    pub fn count_plots(&self, input_method: InputMethod, window: WindowQuery) {
+        self.starting.store(true, Ordering::Relaxed);
+        self.send(GrowerCommand::Count(input_method, window));
     }
 
     pub fn stop(&self) {
-        self.running.store(false, Ordering::Relaxed);
+        self.send(GrowerCommand::Stop);
     }
 
-    pub fn kick(&self) {
-        self.tx
-            .lock()
-            .unwrap()
-            .as_ref()
-            .unwrap()
-            .send(GrowerThreadKick)
-            .unwrap();
+    pub fn pause(&self) {
+        self.pausing.store(true, Ordering::Relaxed);
+        self.send(GrowerCommand::Pause);
+    }
+
+    pub fn resume(&self) {
+        self.pausing.store(false, Ordering::Relaxed);
+        self.send(GrowerCommand::Resume);
+    }
+
+    pub fn selection_changed(&self, sel: Selection) {
+        self.send(GrowerCommand::SelectionChanged(sel));
+    }
+
+    /// The screen reader finished reading the selection, whether it changed or not
+    pub fn scanned(&self) {
+        self.send(GrowerCommand::Scanned);
+    }
+
+    /// Answer the recovery prompt
+    pub fn recover(&self, choice: RecoveryChoice) {
+        self.send(GrowerCommand::Recover(choice));
+    }
+
+    pub fn hotbar_read(&self, counts: BTreeMap<u8, usize>) {
+        self.send(GrowerCommand::Hotbar(counts));
+    }
+
+    /// The sentinel region was captured
+    pub fn frame_captured(&self, frame: Frame) {
+        self.send(GrowerCommand::Frame(frame));
+    }
+
+    /// Stop the session, if any, and wait for the thread to exit. Any keys
+    /// held by the thread are released and the focus is restored on its way
+    /// out. Returns false if the thread didn't finish in time.
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        // dropping the sender stops the session and ends the thread loop
+        if let Some(tx) = lock_ignore_poison(&self.tx).take() {
+            let _ = tx.send(GrowerCommand::Stop);
+        }
+
+        let Some(thread) = lock_ignore_poison(&self.thread).take() else {
+            return true;
+        };
+        if thread.thread().id() == thread::current().id() {
+            // we're panicking on the grower thread itself, unwinding will clean it up
+            return false;
+        }
+
+        let deadline = self.clock.now() + timeout;
+        while !thread.is_finished() {
+            if self.clock.now() >= deadline {
+                error!("Grower thread didn't exit in {timeout:?}");
+                return false;
+            }
+            self.clock.sleep(Duration::from_millis(10));
+        }
+        let _ = thread.join();
+        true
+    }
+
+    fn send(&self, cmd: GrowerCommand) {
+        if let Some(tx) = self.tx.lock().unwrap().as_ref() {
+            let _ = tx.send(cmd);
+        }
+    }
+}
+
+/// Shut down every grower that's still alive. Meant for the panic hook.
+pub fn shutdown_all(timeout: Duration) {
+    let growers = std::mem::take(&mut *lock_ignore_poison(&GROWERS));
+    for grower in growers.iter().filter_map(Weak::upgrade) {
+        grower.shutdown(timeout);
     }
 }
 
+/// We may be called while panicking, so don't panic on a poisoned mutex
+fn lock_ignore_poison<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Message sent to the GrowerThread
+#[derive(Debug)]
+enum GrowerCommand {
+    /// start a new session, once the current one (if any) is over
+    Start(Box<GrowerConfig>),
+    /// count the plots, once the current session (if any) is over
+    Count(InputMethod, WindowQuery),
+    Stop,
+    Pause,
+    Resume,
+    /// the screen reader recognized a different entity
+    SelectionChanged(Selection),
+    Scanned,
+    /// stack counts read from the hotbar, per slot
+    Hotbar(BTreeMap<u8, usize>),
+    Recover(RecoveryChoice),
+    /// the sentinel region was captured
+    Frame(Frame),
+}
+
 #[derive(Debug)]
 struct GrowerThread {
     grower: Arc<Grower>,
-    rx: Receiver<GrowerThreadKick>,
-    window: Option<HWND>,
-    abort_on_missing_selection: bool,
+    rx: Receiver<GrowerCommand>,
+    input: Option<Box<dyn KeyInput>>,
+    selection: Selection,
+    /// number of screen reads so far
+    scans: usize,
+    /// number of screen reads since the last keypress
+    scans_since_input: usize,
+    session: Option<Session>,
+    counting: Option<Counting>,
+    /// start or count request that came while busy, done once we're free
+    queued: Option<GrowerCommand>,
+}
+
+/// Progress of the currently running session
+#[derive(Debug)]
+struct Session {
+    config: GrowerConfig,
+    /// plots still to be visited in the current per-plot pass
+    plots_left: usize,
+    /// plots harvested in the current per-plot pass
+    plots_harvested: usize,
+    /// when the last per-plot pass was started
+    pass_started: Option<Instant>,
+    /// items left in each hotbar slot, if known
+    stock: BTreeMap<u8, usize>,
+    /// slots of the seed keypresses that should have planted an empty plot,
+    /// oldest first. They're counted down once the plot is seen planted.
+    unconfirmed_seeds: VecDeque<u8>,
+    /// why a pause was requested
+    pause_requested: Option<String>,
+    stop_requested: Option<StopReason>,
+    /// answer to the recovery prompt
+    recovery_choice: Option<RecoveryChoice>,
+    /// move to the next plot before the next step
+    skip_plot: bool,
+    /// pauses while the user is busy with the keyboard or mouse
+    idle: Option<IdleWatch>,
+    watchdog: Watchdog,
+    /// pauses while the game can't be seen
+    display: Option<DisplayWatch>,
+    /// reason of the pause requested by the display check, if any
+    display_pause: Option<String>,
+}
+
+impl Session {
+    /// Stop once the watchdog trips
+    fn watchdog_trip(&mut self, trip: Option<WatchdogTrip>) {
+        let Some(trip) = trip else {
+            return;
+        };
+        if self.stop_requested.is_none() {
+            self.stop_requested = Some(StopReason::Watchdog(trip));
+        }
+    }
+
+    /// Pause or stop if any of the watched hotbar stacks runs low
+    fn check_stock(&mut self) {
+        for hotbar_slot in &self.config.hotbar_slots {
+            let Some(&count) = self.stock.get(&hotbar_slot.slot) else {
+                continue;
+            };
+            if count == 0 && self.config.goals.until_out_of_stock {
+                self.stop_requested = Some(StopReason::OutOfStock(hotbar_slot.name.clone()));
+                return;
+            }
+            if !hotbar_slot.is_low(count, self.config.num_objects) {
+                continue;
+            }
+
+            match hotbar_slot.action {
+                StockAction::Alert => {}
+                StockAction::Pause => {
+                    if self.pause_requested.is_none() {
+                        self.pause_requested =
+                            Some(format!("{} is low ({count})", hotbar_slot.name));
+                    }
+                }
+                StockAction::Stop => {
+                    self.stop_requested = Some(StopReason::LowStock(hotbar_slot.name.clone()));
+                }
+            }
+        }
+    }
+}
+
+/// Plot counting in progress
+#[derive(Debug)]
+struct Counting {
+    input_method: InputMethod,
+    window: WindowQuery,
+    stop_requested: bool,
 }
 
 type GrowerThreadHandle = JoinHandle<()>;
 
-/// Message sent to the GrowingThread
-struct GrowerThreadKick;
+/// Why a sleep or a sequence of keypresses was cut short
+enum Interrupt {
+    Pause,
+    Stop(StopReason),
+}
 
 impl GrowerThread {
-    fn spawn(grower: Arc<Grower>, rx: Receiver<GrowerThreadKick>) -> GrowerThreadHandle {
-        thread::spawn(move || {
-            let inner = GrowerThread {
-                grower,
-                rx,
-                window: None,
-                abort_on_missing_selection: false,
-            };
-            inner.run();
-        })
+    fn spawn(grower: Arc<Grower>, rx: Receiver<GrowerCommand>) -> GrowerThreadHandle {
+        thread::spawn(move || Self::new(grower, rx).run())
+    }
+
+    fn new(grower: Arc<Grower>, rx: Receiver<GrowerCommand>) -> Self {
+        Self {
+            grower,
+            rx,
+            input: None,
+            selection: Selection::default(),
+            scans: 0,
+            scans_since_input: 0,
+            session: None,
+            counting: None,
+            queued: None,
+        }
     }
 
     fn run(mut self) {
+        while let Ok(cmd) = self.rx.recv() {
+            self.handle_command(cmd);
+            while self.counting.is_some() || self.session.is_some() {
+                if self.counting.is_some() {
+                    self.finish_counting();
+                } else {
+                    self.finish_session();
+                }
+                if let Some(cmd) = self.queued.take() {
+                    self.handle_command(cmd);
+                }
+            }
+        }
+    }
+
+    fn finish_counting(&mut self) {
+        let result = self.count_plots();
+        self.counting = None;
+        self.input = None;
+        match result {
+            Ok(num_plots) => {
+                *self.grower.counted_plots.lock().unwrap() = Some(num_plots);
+                self.transition(
+                    GrowerEvent::ActionDone,
+                    &format!("Counted {num_plots} plots"),
+                );
+            }
+            Err(reason) => self.transition(GrowerEvent::Stop(reason.clone()), &reason.to_string()),
+        }
+    }
+
+    fn finish_session(&mut self) {
+        let reason = self.run_session();
+        self.session = None;
+        self.grower.pausing.store(false, Ordering::Relaxed);
+        // release the keys and restore the focus if we were interrupted mid-sequence
+        self.input = None;
+        self.transition(GrowerEvent::Stop(reason.clone()), &reason.to_string());
+    }
+
+    fn handle_command(&mut self, cmd: GrowerCommand) {
+        match cmd {
+            GrowerCommand::Start(config) => {
+                if self.session.is_some() || self.counting.is_some() {
+                    log!("Starting once the current session is over");
+                    self.queued = Some(GrowerCommand::Start(config));
+                    return;
+                }
+                self.grower
+                    .garden
+                    .lock()
+                    .unwrap()
+                    .start_cycle(config.num_objects);
+                let now = self.grower.clock.now();
+                *self.grower.progress.lock().unwrap() = Some(config.goals.start(now));
+                let idle = config
+                    .idle_policy
+                    .map(|policy| IdleWatch::new(policy, self.grower.activity.clone(), now));
+                let watchdog = Watchdog::new(config.watchdog.clone());
+                let display = config
+                    .display_check
+                    .then(|| DisplayWatch::new(config.frozen_after, now));
+                self.session = Some(Session {
+                    plots_left: 0,
+                    plots_harvested: 0,
+                    pass_started: None,
+                    stock: config.known_seeds(),
+                    unconfirmed_seeds: VecDeque::new(),
+                    config: *config,
+                    pause_requested: None,
+                    stop_requested: None,
+                    recovery_choice: None,
+                    skip_plot: false,
+                    idle,
+                    watchdog,
+                    display,
+                    display_pause: None,
+                });
+                self.transition(GrowerEvent::Start, "Session started");
+                self.grower.starting.store(false, Ordering::Relaxed);
+            }
+            GrowerCommand::Count(input_method, window) => {
+                if self.session.is_some() || self.counting.is_some() {
+                    log!("Counting once the current session is over");
+                    self.queued = Some(GrowerCommand::Count(input_method, window));
+                    return;
+                }
+                self.counting = Some(Counting {
+                    input_method,
+                    window,
+                    stop_requested: false,
+                });
+                self.transition(GrowerEvent::Count, "Counting started");
+                self.grower.starting.store(false, Ordering::Relaxed);
+            }
+            GrowerCommand::Stop => {
+                if self.queued.take().is_some() {
+                    self.grower.starting.store(false, Ordering::Relaxed);
+                }
+                if let Some(session) = &mut self.session {
+                    session.stop_requested = Some(StopReason::UserRequest);
+                }
+                if let Some(counting) = &mut self.counting {
+                    counting.stop_requested = true;
+                }
+            }
+            GrowerCommand::Pause => match &mut self.session {
+                Some(session) => session.pause_requested = Some("Paused by the user".to_string()),
+                // nothing to pause
+                None => self.grower.pausing.store(false, Ordering::Relaxed),
+            },
+            GrowerCommand::Resume => {
+                if let Some(session) = &mut self.session {
+                    session.pause_requested = None;
+                }
+            }
+            GrowerCommand::Recover(choice) => {
+                if let Some(session) = &mut self.session {
+                    session.recovery_choice = Some(choice);
+                }
+            }
+            GrowerCommand::Hotbar(counts) => {
+                if let Some(session) = &mut self.session {
+                    session.stock.extend(counts);
+                    session.check_stock();
+                }
+            }
+            GrowerCommand::Frame(frame) => {
+                let now = self.grower.clock.now();
+                if let Some(display) = self.session.as_mut().and_then(|s| s.display.as_mut()) {
+                    display.frame(now, frame);
+                }
+            }
+            GrowerCommand::SelectionChanged(sel) => {
+                self.selection = sel;
+            }
+            GrowerCommand::Scanned => {
+                self.scans += 1;
+                self.scans_since_input += 1;
+                if let Some(session) = &mut self.session {
+                    let paused = self.grower.machine.lock().unwrap().state().is_paused();
+                    if !paused {
+                        let recognized = self.selection.state != CurrentlySelected::None;
+                        let trip = session.watchdog.read(recognized);
+                        session.watchdog_trip(trip);
+                    }
+                }
+                // the first scan could have captured the screen before the keypress took effect
+                if self.session.is_some() && self.scans_since_input >= 2 {
+                    let now = self.grower.clock.now();
+                    let change = self
+                        .grower
+                        .garden
+                        .lock()
+                        .unwrap()
+                        .observe(now, &self.selection);
+                    match change {
+                        Some(Change::StageDone(done)) => {
+                            let mut stats = self.grower.stats.lock().unwrap();
+                            stats.record(&done.crop, done.from, done.to, done.took);
+                            if let Err(e) = stats.save() {
+                                error!("Failed to save growth stats: {e}");
+                            }
+                        }
+                        Some(Change::Planted(crop)) => self.seed_used(&crop),
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply all commands sent since the last call, without blocking
+    fn poll_commands(&mut self) {
+        loop {
+            match self.rx.try_recv() {
+                Ok(cmd) => self.handle_command(cmd),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.handle_command(GrowerCommand::Stop);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Press the next target key until the selections start repeating.
+    /// Returns the number of plots and leaves the initial one selected.
+    fn count_plots(&mut self) -> Result<usize, StopReason> {
+        let counting = self.counting.as_ref().unwrap();
+        let desktop = &self.grower.desktop;
+        let Some(window) = desktop.find(&counting.window) else {
+            return Err(StopReason::WindowNotFound);
+        };
+        let method = counting.input_method;
+        self.input = Some(desktop.input(window, method, self.grower.clock.clone()));
+
+        let mut readings = Vec::new();
+        loop {
+            self.wait_for_counting_read()?;
+            if self.selection.state == CurrentlySelected::None {
+                return Err(StopReason::NothingSelected);
+            }
+            readings.push(self.selection.clone());
+
+            let cycle = garden::cycle_length(&readings);
+            if let Cycle::Length(num_plots) = cycle {
+                if num_plots == 1 {
+                    return Err(StopReason::PlotsLookAlike);
+                }
+                // we're at the last plot, go back to the first one
+                self.next_target();
+                let now = self.grower.clock.now();
+                self.grower
+                    .garden
+                    .lock()
+                    .unwrap()
+                    .counted(now, &readings[..num_plots]);
+                return Ok(num_plots);
+            }
+            if readings.len() >= MAX_COUNTING_READS {
+                return Err(match cycle {
+                    Cycle::Ambiguous(a, b) => StopReason::AmbiguousCycle(a, b),
+                    _ => StopReason::NoCycleFound,
+                });
+            }
+            self.next_target();
+        }
+    }
+
+    fn next_target(&mut self) {
+        self.input().begin();
+        self.press(0x59); // Y key (next)
+        self.grower.clock.sleep(Duration::from_millis(150));
+        self.input().end();
+    }
+
+    /// Same as [`Self::wait_for_fresh_read`], but for plot counting
+    fn wait_for_counting_read(&mut self) -> Result<(), StopReason> {
+        let target = self.scans + 2;
+        let deadline = self.grower.clock.now() + FRESH_READ_TIMEOUT;
+        while self.scans < target {
+            if self.grower.clock.now() >= deadline {
+                return Err(StopReason::NoScreenReads);
+            }
+            self.grower.clock.sleep(Duration::from_millis(100));
+            self.poll_commands();
+            if self.counting.as_ref().unwrap().stop_requested {
+                return Err(StopReason::UserRequest);
+            }
+        }
+        Ok(())
+    }
+
+    fn run_session(&mut self) -> StopReason {
         loop {
-            match self.rx.recv() {
-                Err(_) => break,
-                Ok(_) => {
-                    self.window = find_window("Project Gorgon");
-                    if self.window.is_none() {
-                        nwg::error_message("Error", "Can't find Project Gorgon window");
-                        continue;
+            self.check_idle();
+            self.check_display();
+            self.check_watchdog();
+            let result = self
+                .can_continue()
+                .and_then(|_| self.ensure_input())
+                .and_then(|_| self.step());
+            match result {
+                Ok(()) => {}
+                Err(Interrupt::Stop(StopReason::SelectionChanged)) => match self.ask_recovery() {
+                    Ok(RecoveryChoice::Resume) => {}
+                    Ok(RecoveryChoice::Skip) => self.session_mut().skip_plot = true,
+                    Ok(RecoveryChoice::Stop) => return StopReason::SelectionChanged,
+                    Err(reason) => return reason,
+                },
+                Err(Interrupt::Stop(reason)) if reason.is_recoverable() => {
+                    // let the user fix it and resume instead of losing the session.
+                    // The game window could have changed in the meantime
+                    self.input = None;
+                    self.session_mut().pause_requested =
+                        Some(format!("{reason}, resume when fixed"));
+                    if let Err(reason) = self.wait_while_paused() {
+                        return reason;
                     }
-                    self.grower.running.store(true, Ordering::Relaxed);
-                    while self.can_continue().is_ok() {
-                        let _ = self.do_round();
+                }
+                Err(Interrupt::Pause) => {
+                    if let Err(reason) = self.wait_while_paused() {
+                        return reason;
                     }
-                    self.grower.running.store(false, Ordering::Relaxed);
                 }
+                Err(Interrupt::Stop(reason)) => return reason,
             }
         }
     }
 
-    fn do_round(&mut self) -> Result<(), ()> {
-        let grower = &self.grower;
-        if grower.num_objects.load(Ordering::Relaxed) == 0 {
+    /// Pause and wait for the user to decide how to go on, or make the
+    /// configured choice once the time is up
+    fn ask_recovery(&mut self) -> Result<RecoveryChoice, StopReason> {
+        const REASON: StopReason = StopReason::SelectionChanged;
+        if let Some(input) = &mut self.input {
+            input.end();
+        }
+
+        let auto_choice = self.config().recovery_auto_choice;
+        let deadline = self.grower.clock.now() + self.config().recovery_timeout;
+        *self.grower.recovery.lock().unwrap() = Some(RecoveryPrompt {
+            reason: REASON,
+            deadline,
+            auto_choice,
+        });
+        self.session_mut().recovery_choice = None;
+        self.transition(
+            GrowerEvent::Pause,
+            &format!("{REASON}, waiting for a decision"),
+        );
+
+        let choice = loop {
+            if let Some(reason) = &self.session().stop_requested {
+                *self.grower.recovery.lock().unwrap() = None;
+                return Err(reason.clone());
+            }
+            if let Some(choice) = self.session_mut().recovery_choice.take() {
+                break choice;
+            }
+            if self.grower.clock.now() >= deadline {
+                log!("No decision in time, going with {auto_choice:?}");
+                break auto_choice;
+            }
+            self.grower.clock.sleep(Duration::from_millis(100));
+            self.poll_commands();
+        };
+        *self.grower.recovery.lock().unwrap() = None;
+
+        let event = match choice {
+            RecoveryChoice::Resume => GrowerEvent::Resume,
+            RecoveryChoice::Skip => GrowerEvent::Skip,
+            RecoveryChoice::Stop => return Ok(choice),
+        };
+        self.transition(event, &format!("Recovered with {choice:?}"));
+        Ok(choice)
+    }
+
+    /// Find the game window, unless we have it already. The handle goes
+    /// stale when the window is recreated, e.g. by restarting the game.
+    fn ensure_input(&mut self) -> Result<(), Interrupt> {
+        let desktop = &self.grower.desktop;
+        if self
+            .input
+            .as_ref()
+            .is_some_and(|input| desktop.is_alive(input.window()))
+        {
             return Ok(());
         }
 
-        use CurrentlySelected as C;
-        let sel: C = grower
-            .cur_selected
-            .load(Ordering::Relaxed)
-            .try_into()
-            .unwrap();
-        match sel {
-            C::None | C::Growing => {
-                self.update_status_str(Some("Waiting 5s ..."), Some(""));
-                self.interruptible_sleep(Duration::from_millis(5000))?;
-            }
-            C::Thisty => {
-                self.abort_on_missing_selection = true;
-                self.update_status_str(Some("Watering!"), Some(""));
-                self.do_use_round()?;
-            }
-            C::Hungry => {
-                self.abort_on_missing_selection = true;
-                self.update_status_str(Some("Fertilizing!"), Some(""));
-                self.do_use_round()?;
-            }
-            C::Ripe => {
-                self.abort_on_missing_selection = false;
-                self.update_status_str(Some("Harvesting!"), Some(""));
-                self.do_use_round()?;
-
-                assert!(self.grower.num_rounds.load(Ordering::Relaxed) > 0);
-                self.grower.num_rounds.fetch_sub(1, Ordering::Relaxed);
+        let Some(window) = desktop.find(&self.config().window) else {
+            return Err(Interrupt::Stop(StopReason::WindowNotFound));
+        };
+        let method = self.config().input_method;
+        self.input = Some(desktop.input(window, method, self.grower.clock.clone()));
+        Ok(())
+    }
+
+    /// Handle of the game window, found again if the one we had is gone
+    fn game_window(&self) -> Option<HWND> {
+        let desktop = &self.grower.desktop;
+        match &self.input {
+            Some(input) if desktop.is_alive(input.window()) => Some(input.window()),
+            _ => desktop.find(&self.config().window),
+        }
+    }
+
+    /// Do whatever the current state requires, then move on to the next one
+    fn step(&mut self) -> Result<(), Interrupt> {
+        if std::mem::take(&mut self.session_mut().skip_plot) {
+            return self.skip_plot();
+        }
+        if self.config().per_plot {
+            return self.step_per_plot();
+        }
+
+        use GrowerEvent as E;
+        use GrowerState as S;
+
+        let state = self.grower.machine.lock().unwrap().state().clone();
+        match state {
+            S::Waiting => self.wait_for_crop()?,
+            S::Watering => {
+                let note = self.do_verified_use_round(CurrentlySelected::Thisty)?;
+                self.transition(E::ActionDone, &format!("Watered all objects{note}"));
+            }
+            S::Fertilizing => {
+                let note = self.do_verified_use_round(CurrentlySelected::Hungry)?;
+                self.transition(E::ActionDone, &format!("Fertilized all objects{note}"));
+            }
+            S::Harvesting => {
+                let note = self.do_verified_use_round(CurrentlySelected::Ripe)?;
+
+                let num_objects = self.config().num_objects;
+                let summary = self.add_progress(1, num_objects);
                 self.can_continue()?;
+                self.transition(
+                    E::ActionDone,
+                    &format!("Harvested all objects, {summary}{note}"),
+                );
+            }
+            S::Replanting => {
+                self.do_replant()?;
+                self.transition(E::ActionDone, "Replanted all objects");
+            }
+            S::Idle | S::Paused { .. } | S::Counting | S::Stopped { .. } => {
+                unreachable!("grower stepped in {state} state")
+            }
+        };
 
-                self.update_status_str(Some("Replanting!"), Some(""));
-                let prev_hwnd = set_hwnd_focus(self.window.unwrap());
-                for i in 0..5 {
-                    send_keypress(0x31 + i); // 1 key (or 2, 3, 4)
-                    self.interruptible_sleep(std::time::Duration::from_millis(225))?;
-                    send_keypress(0x31 + i); // 1 key (or 2, 3, 4)
-                    self.interruptible_sleep(std::time::Duration::from_millis(225))?;
-                    send_keypress(0x31 + i); // 1 key (or 2, 3, 4)
-                    self.interruptible_sleep(std::time::Duration::from_millis(225))?;
-                }
+        Ok(())
+    }
 
-                self.interruptible_sleep(std::time::Duration::from_millis(150))?;
-                send_keypress(0x59); // Y key (next)
-                self.interruptible_sleep(std::time::Duration::from_millis(150))?;
-                set_hwnd_focus(prev_hwnd);
+    /// Same as [`Self::step`], but instead of using all objects at once, go
+    /// through them one by one and only do what the selected one needs
+    fn step_per_plot(&mut self) -> Result<(), Interrupt> {
+        use GrowerEvent as E;
+        use GrowerState as S;
+
+        let state = self.grower.machine.lock().unwrap().state().clone();
+        let plot = self.config().num_objects - self.session().plots_left + 1;
+        match state {
+            S::Waiting if self.session().plots_left == 0 => {
+                // start a pass once anything needs to be done, or should by now
+                if self.wants_action() || self.action_due() {
+                    let now = self.grower.clock.now();
+                    let session = self.session_mut();
+                    session.plots_left = session.config.num_objects;
+                    session.plots_harvested = 0;
+                    session.pass_started = Some(now);
+                    self.visit_plot()?;
+                } else {
+                    self.interruptible_sleep(Duration::from_millis(5000))?;
+                }
+            }
+            S::Waiting => self.visit_plot()?,
+            S::Watering => {
+                let note = self.do_verified_use_round(CurrentlySelected::Thisty)?;
+                self.next_plot()?;
+                self.transition(E::ActionDone, &format!("Watered plot {plot}{note}"));
+            }
+            S::Fertilizing => {
+                let note = self.do_verified_use_round(CurrentlySelected::Hungry)?;
+                self.next_plot()?;
+                self.transition(E::ActionDone, &format!("Fertilized plot {plot}{note}"));
+            }
+            S::Harvesting => {
+                let note = self.do_verified_use_round(CurrentlySelected::Ripe)?;
+                let now = self.grower.clock.now();
+                self.grower.garden.lock().unwrap().harvested(now);
+                self.session_mut().plots_harvested += 1;
+                self.add_progress(0, 1);
+                self.transition(E::ActionDone, &format!("Harvested plot {plot}{note}"));
+            }
+            S::Replanting => {
+                self.do_replant_plot()?;
+                self.next_plot()?;
+                self.transition(E::ActionDone, &format!("Replanted plot {plot}"));
+            }
+            S::Idle | S::Paused { .. } | S::Counting | S::Stopped { .. } => {
+                unreachable!("grower stepped in {state} state")
             }
         };
 
         Ok(())
     }
 
-    fn do_use_round(&self) -> Result<(), ()> {
-        let extra_delay_secs = self.grower.extra_delay_secs.load(Ordering::Relaxed);
-        if extra_delay_secs > 0 {
-            self.update_status_str(None, Some("Waiting extra seconds ..."));
-            self.interruptible_sleep(Duration::from_secs(extra_delay_secs as u64))?;
-            self.update_status_str(None, Some(""));
+    /// Leave the selected plot as it is and go to the next one
+    fn skip_plot(&mut self) -> Result<(), Interrupt> {
+        if self.config().per_plot && self.session().plots_left > 0 {
+            return self.next_plot();
         }
 
-        let prev_hwnd = set_hwnd_focus(self.window.unwrap());
-        for _ in 0..self.grower.num_objects.load(Ordering::Relaxed) {
-            send_keypress(0x55); // U key (use)
-            self.interruptible_sleep(std::time::Duration::from_millis(150))?;
-            send_keypress(0x55); // U key (use)
-            self.interruptible_sleep(std::time::Duration::from_millis(150))?;
-            send_keypress(0x59); // Y key (next)
-            self.interruptible_sleep(std::time::Duration::from_millis(150))?;
+        self.input().begin();
+        self.press(0x59); // Y key (next)
+        self.interruptible_sleep(std::time::Duration::from_millis(150))?;
+        self.input().end();
+        Ok(())
+    }
+
+    /// Whether any plot is predicted to need something since the last pass
+    fn action_due(&self) -> bool {
+        let stats = self.grower.stats.lock().unwrap();
+        let Some(due) = self.grower.garden.lock().unwrap().next_action(&stats) else {
+            return false;
+        };
+        let now = self.grower.clock.now();
+        due <= now
+            && self
+                .session()
+                .pass_started
+                .is_none_or(|started| started < due)
+    }
+
+    /// Per-plot pass: act on the currently selected plot, or skip it if it
+    /// doesn't need anything
+    fn visit_plot(&mut self) -> Result<(), Interrupt> {
+        let sel = self.selection.state;
+        let plot = self.config().num_objects - self.session().plots_left + 1;
+        if self.wants_action() {
+            self.transition(
+                GrowerEvent::Selected(sel),
+                &format!("Plot {plot} is {sel:?}"),
+            );
+            return Ok(());
         }
-        set_hwnd_focus(prev_hwnd);
 
+        log!("Skipping plot {plot} ({sel:?})");
+        self.next_plot()
+    }
+
+    /// Per-plot pass: select the next target and read it
+    fn next_plot(&mut self) -> Result<(), Interrupt> {
+        self.input().begin();
+        self.press(0x59); // Y key (next)
+        self.interruptible_sleep(std::time::Duration::from_millis(150))?;
+        self.input().end();
+
+        let session = self.session_mut();
+        session.plots_left -= 1;
+        // count the pass as a round only if there was anything to harvest
+        if session.plots_left == 0 && session.plots_harvested > 0 {
+            let plots_harvested = session.plots_harvested;
+            let summary = self.add_progress(1, 0);
+            log!("Harvested {plots_harvested} plots, {summary}");
+        }
+
+        if !self.wait_for_fresh_read()? {
+            return Err(Interrupt::Stop(StopReason::NoScreenReads));
+        }
         Ok(())
     }
 
-    fn can_continue(&self) -> std::result::Result<(), ()> {
-        if !self.grower.running.load(Ordering::Relaxed) {
-            return Err(());
+    fn wait_for_crop(&mut self) -> Result<(), Interrupt> {
+        let sel = self.selection.state;
+        if !self.wants_action() {
+            return self.interruptible_sleep(Duration::from_millis(5000));
         }
 
-        if self.grower.num_rounds.load(Ordering::Relaxed) == 0 {
-            self.update_status_str(Some("Finished all rounds"), Some(""));
-            return Err(());
+        self.transition(
+            GrowerEvent::Selected(sel),
+            &format!("{sel:?} crop selected"),
+        );
+        Ok(())
+    }
+
+    fn wait_while_paused(&mut self) -> Result<(), StopReason> {
+        // give the focus back for the time being
+        if let Some(input) = &mut self.input {
+            input.end();
         }
-        if self.abort_on_missing_selection
-            && CurrentlySelected::try_from(self.grower.cur_selected.load(Ordering::Relaxed))
-                .unwrap()
-                == CurrentlySelected::None
-        {
-            self.update_status_str(Some(""), Some("Selection changed abruptly! Stopping"));
-            return Err(());
+        let reason = self.session().pause_requested.clone().unwrap_or_default();
+        self.transition(GrowerEvent::Pause, &reason);
+        self.grower.pausing.store(false, Ordering::Relaxed);
+        let mut resume_reason = "Resumed by the user";
+        while self.session().pause_requested.is_some() {
+            if let Some(reason) = &self.session().stop_requested {
+                return Err(reason.clone());
+            }
+            self.grower.clock.sleep(Duration::from_millis(100));
+            self.poll_commands();
+            if self.check_idle() {
+                resume_reason = "Nobody is using the keyboard or mouse anymore";
+            }
+            if self.check_display() {
+                resume_reason = "The game can be seen again";
+            }
         }
+        self.transition(GrowerEvent::Resume, resume_reason);
         Ok(())
     }
 
-    fn interruptible_sleep(&self, dur: Duration) -> std::result::Result<(), ()> {
-        const SLEEP_TICK_MS: u128 = 100;
-        if dur.as_millis() < SLEEP_TICK_MS {
-            std::thread::sleep(dur);
-            return self.can_continue();
+    fn do_use_round(&mut self) -> Result<(), Interrupt> {
+        let crop_delay = self
+            .config()
+            .crop_plan(&self.selection.crop)
+            .map_or(0, |plan| plan.extra_delay_secs);
+        let extra_delay = self.config().extra_delay + Duration::from_secs(crop_delay as u64);
+        if !extra_delay.is_zero() {
+            self.interruptible_sleep(extra_delay)?;
         }
 
-        for _ in 0..(dur.as_millis() / SLEEP_TICK_MS) {
-            std::thread::sleep(std::time::Duration::from_millis(SLEEP_TICK_MS as u64));
-            self.can_continue()?;
+        // in the per-plot mode only the selected object is used, and it stays selected
+        let per_plot = self.config().per_plot;
+        let num_objects = if per_plot {
+            1
+        } else {
+            self.config().num_objects
+        };
+
+        self.input().begin();
+        for _ in 0..num_objects {
+            self.press(0x55); // U key (use)
+            self.interruptible_sleep(std::time::Duration::from_millis(150))?;
+            self.press(0x55); // U key (use)
+            self.interruptible_sleep(std::time::Duration::from_millis(150))?;
+            if !per_plot {
+                self.press(0x59); // Y key (next)
+                self.interruptible_sleep(std::time::Duration::from_millis(150))?;
+            }
         }
+        self.input().end();
+        self.round_done();
 
         Ok(())
     }
 
-    fn update_status_str(&self, s0: Option<&str>, s1: Option<&str>) {
-        let mut s = self.grower.status_str.lock().unwrap();
-        if let Some(s0) = s0 {
-            s[0].clear();
-            s[0].push_str(s0);
+    fn do_replant(&mut self) -> Result<(), Interrupt> {
+        let crops: Vec<String> = {
+            let garden = self.grower.garden.lock().unwrap();
+            garden
+                .plots()
+                .iter()
+                .map(|plot| plot.crop.clone())
+                .collect()
+        };
+        // plant the right seed for every plot if we know them all
+        let seed_slots: Option<Vec<u8>> = crops.iter().map(|crop| self.seed_slot(crop)).collect();
+
+        self.input().begin();
+        if let Some(seed_slots) = seed_slots {
+            for slot in seed_slots {
+                if let Some(slot) = self.usable_slot(slot)? {
+                    self.plant(slot);
+                    self.interruptible_sleep(std::time::Duration::from_millis(225))?;
+                }
+            }
+        } else {
+            let presses = self.config().replant_presses;
+            for slot in self.replant_slots_left()? {
+                for _ in 0..presses {
+                    if self.slot_empty(slot) {
+                        break;
+                    }
+                    self.plant(slot);
+                    self.interruptible_sleep(std::time::Duration::from_millis(225))?;
+                }
+            }
         }
-        if let Some(s1) = s1 {
-            s[1].clear();
-            s[1].push_str(s1);
+
+        self.interruptible_sleep(std::time::Duration::from_millis(150))?;
+        self.press(0x59); // Y key (next)
+        self.interruptible_sleep(std::time::Duration::from_millis(150))?;
+        self.input().end();
+        self.round_done();
+
+        Ok(())
+    }
+
+    /// Plant a single seed where the harvested plot was
+    fn do_replant_plot(&mut self) -> Result<(), Interrupt> {
+        let crop = {
+            let garden = self.grower.garden.lock().unwrap();
+            garden.plots()[garden.cursor()].crop.clone()
+        };
+
+        self.input().begin();
+        if let Some(slot) = self.seed_slot(&crop) {
+            if let Some(slot) = self.usable_slot(slot)? {
+                self.plant(slot);
+                self.interruptible_sleep(std::time::Duration::from_millis(225))?;
+            }
+        } else {
+            for slot in self.replant_slots_left()? {
+                self.plant(slot);
+                self.interruptible_sleep(std::time::Duration::from_millis(225))?;
+            }
+        }
+        self.input().end();
+        self.round_done();
+
+        Ok(())
+    }
+
+    /// Use the seeds from the hotbar slot. They're counted down only once
+    /// the plot is seen planted, and only as many presses as there are empty
+    /// plots can plant anything.
+    fn plant(&mut self, slot: u8) {
+        self.press(slot_key(slot));
+        let empty_plots = self.grower.garden.lock().unwrap().empty_plots();
+        let unconfirmed = &mut self.session_mut().unconfirmed_seeds;
+        if unconfirmed.len() < empty_plots {
+            unconfirmed.push_back(slot);
+        }
+    }
+
+    /// An empty plot was seen planted with the crop
+    fn seed_used(&mut self, crop: &str) {
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        let planned = session
+            .config
+            .crop_plan(crop)
+            .and_then(|plan| plan.seed_slot);
+        let unconfirmed = &mut session.unconfirmed_seeds;
+        let idx = planned.and_then(|slot| unconfirmed.iter().position(|&s| s == slot));
+        let Some(slot) = unconfirmed.remove(idx.unwrap_or(0)) else {
+            return;
+        };
+        // one of our presses planted it
+        session.watchdog.state_changed();
+        let Some(seeds) = session.stock.get_mut(&slot) else {
+            return;
+        };
+        *seeds = seeds.saturating_sub(1);
+        if *seeds == 0 {
+            log!("Hotbar slot {slot} is out of seeds");
+            if session.config.goals.until_out_of_stock {
+                session.stop_requested = Some(StopReason::OutOfSeeds(Some(slot)));
+            }
+        }
+    }
+
+    fn slot_empty(&self, slot: u8) -> bool {
+        self.session().stock.get(&slot) == Some(&0)
+    }
+
+    /// Slot to plant from instead of `slot` if that one is empty, following
+    /// the configured policy. None if the plot should be left empty.
+    fn usable_slot(&self, slot: u8) -> Result<Option<u8>, Interrupt> {
+        if !self.slot_empty(slot) {
+            return Ok(Some(slot));
+        }
+
+        match self.config().empty_slot_policy {
+            EmptySlotPolicy::Skip => Ok(None),
+            EmptySlotPolicy::Fallback => {
+                let fallback = self.replant_slots_left()?.first().copied();
+                if fallback.is_none() {
+                    return Err(Interrupt::Stop(StopReason::OutOfSeeds(None)));
+                }
+                Ok(fallback)
+            }
+            EmptySlotPolicy::Stop => Err(Interrupt::Stop(StopReason::OutOfSeeds(Some(slot)))),
         }
     }
-}
 
-fn find_window(name: &str) -> Option<HWND> {
-    let c_name = CString::new(name).unwrap();
+    /// Generic replant slots that still have seeds
+    fn replant_slots_left(&self) -> Result<Vec<u8>, Interrupt> {
+        let config = self.config();
+        let slots: Vec<u8> = config
+            .replant_slots
+            .iter()
+            .map(|replant_slot| replant_slot.slot)
+            .filter(|&slot| !self.slot_empty(slot))
+            .collect();
+        if slots.is_empty() && config.empty_slot_policy == EmptySlotPolicy::Stop {
+            return Err(Interrupt::Stop(StopReason::OutOfSeeds(None)));
+        }
+        Ok(slots)
+    }
+
+    fn seed_slot(&self, crop: &str) -> Option<u8> {
+        self.config().crop_plan(crop)?.seed_slot
+    }
+
+    /// Whether the selected crop needs anything we're supposed to do
+    fn wants_action(&self) -> bool {
+        let sel = &self.selection;
+        if sel.state == CurrentlySelected::Hungry {
+            let plan = self.config().crop_plan(&sel.crop);
+            if plan.is_some_and(|plan| !plan.fertilize) {
+                return false;
+            }
+        }
+        needs_action(sel.state)
+    }
 
-    let hwnd = unsafe { FindWindowA(std::ptr::null_mut(), c_name.as_ptr()) };
-    if hwnd.is_null() {
-        return None;
+    /// Use all objects, then re-read the selection to check if it changed as
+    /// expected. Retry a few times if it didn't. Returns a note for the
+    /// transition log.
+    fn do_verified_use_round(&mut self, sel: CurrentlySelected) -> Result<&'static str, Interrupt> {
+        let mut note = "";
+        let retries = self.config().action_retries;
+        for attempt in 0..=retries {
+            self.do_use_round()?;
+            if self.wait_for_fresh_read()? && action_took_effect(sel, self.selection.state) {
+                self.session_mut().watchdog.state_changed();
+                return Ok(note);
+            }
+
+            log!(
+                "{sel:?} action not confirmed (attempt {} of {})",
+                attempt + 1,
+                retries + 1
+            );
+            // posted key messages can be silently ignored by the game, so try
+            // the foreground method from now on
+            if self.input().method() == InputMethod::Background {
+                self.input().fall_back();
+                self.input().end();
+                note = " (background input ignored, using foreground from now on)";
+            }
+        }
+
+        Err(Interrupt::Stop(StopReason::ActionNotConfirmed(sel)))
+    }
+
+    /// Wait until the screen was read again after whatever we just did.
+    /// Returns false if the screen reader didn't catch up in time.
+    fn wait_for_fresh_read(&mut self) -> Result<bool, Interrupt> {
+        self.grower.awaiting_read.store(true, Ordering::Relaxed);
+        let fresh = self.wait_for_scans();
+        self.grower.awaiting_read.store(false, Ordering::Relaxed);
+        fresh
+    }
+
+    fn wait_for_scans(&mut self) -> Result<bool, Interrupt> {
+        // the first scan could have captured the screen before we were done
+        let target = self.scans + 2;
+        let deadline = self.grower.clock.now() + FRESH_READ_TIMEOUT;
+        while self.scans < target {
+            if self.grower.clock.now() >= deadline {
+                return Ok(false);
+            }
+            self.interruptible_sleep(Duration::from_millis(100))?;
+        }
+        Ok(true)
+    }
+
+    fn config(&self) -> &GrowerConfig {
+        &self.session().config
+    }
+
+    fn session(&self) -> &Session {
+        self.session.as_ref().unwrap()
+    }
+
+    fn session_mut(&mut self) -> &mut Session {
+        self.session.as_mut().unwrap()
+    }
+
+    fn input(&mut self) -> &mut dyn KeyInput {
+        self.input.as_deref_mut().unwrap()
+    }
+
+    /// Press the key and keep track of which plot it leaves selected
+    fn press(&mut self, key: u16) {
+        self.input().press(key);
+        self.scans_since_input = 0;
+        let now = self.grower.clock.now();
+        *self.grower.last_input.lock().unwrap() = Some(now);
+        // counting plots runs without a session
+        if let Some(session) = &mut self.session {
+            let trip = session.watchdog.pressed(now);
+            session.watchdog_trip(trip);
+        }
+        if key == 0x59 {
+            self.grower.garden.lock().unwrap().next();
+        }
     }
 
-    Some(hwnd)
+    /// All keys of an action round were pressed
+    fn round_done(&mut self) {
+        let session = self.session_mut();
+        let trip = session.watchdog.round_done();
+        session.watchdog_trip(trip);
+    }
+
+    /// Stop if the grower stays in an action state for too long
+    fn check_watchdog(&mut self) {
+        let now = self.grower.clock.now();
+        let state = self.grower.machine.lock().unwrap().state().clone();
+        let session = self.session_mut();
+        let trip = session.watchdog.check_state(now, &state);
+        session.watchdog_trip(trip);
+    }
+
+    /// Count harvested rounds and plants towards the goals. Returns the
+    /// progress summary.
+    fn add_progress(&self, rounds: usize, plants: usize) -> String {
+        let now = self.grower.clock.now();
+        let mut progress = self.grower.progress.lock().unwrap();
+        let progress = progress.as_mut().unwrap();
+        progress.rounds += rounds;
+        progress.plants += plants;
+        progress.summary(now)
+    }
+
+    fn transition(&self, event: GrowerEvent, reason: &str) {
+        let now = self.grower.clock.now();
+        self.grower
+            .machine
+            .lock()
+            .unwrap()
+            .handle(now, event, reason);
+    }
+
+    /// Pause while the user is busy with the keyboard or mouse, and resume
+    /// such pause once they're done. Returns true if it resumed.
+    fn check_idle(&mut self) -> bool {
+        let now = self.grower.clock.now();
+        let session = self.session.as_mut().unwrap();
+        let Some(idle) = &mut session.idle else {
+            return false;
+        };
+        if let Some(at) = last_bot_input() {
+            idle.own_input(at);
+        }
+        match session.pause_requested.as_deref() {
+            None if idle.user_active(now) => {
+                session.pause_requested = Some(USER_ACTIVE.to_string());
+                false
+            }
+            Some(USER_ACTIVE) if idle.user_quiet(now) => {
+                session.pause_requested = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Pause while the game can't be seen, and resume such pause once it
+    /// can again. Returns true if it resumed.
+    fn check_display(&mut self) -> bool {
+        if self.session().display.is_none() {
+            return false;
+        }
+        let now = self.grower.clock.now();
+        let minimized = self
+            .game_window()
+            .map(|hwnd| self.grower.desktop.is_minimized(hwnd));
+        let session = self.session.as_mut().unwrap();
+        let display = session.display.as_ref().unwrap();
+        let problem = display.problem(now, minimized);
+        match (&session.pause_requested, problem) {
+            (None, Some(problem)) => {
+                session.pause_requested = Some(problem.to_string());
+                session.display_pause = session.pause_requested.clone();
+                false
+            }
+            (Some(reason), None) if session.display_pause.as_ref() == Some(reason) => {
+                session.pause_requested = None;
+                session.display_pause = None;
+                // the game could have been restarted in the meantime
+                self.input = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn can_continue(&self) -> Result<(), Interrupt> {
+        let session = self.session.as_ref().unwrap();
+        if let Some(reason) = &session.stop_requested {
+            return Err(Interrupt::Stop(reason.clone()));
+        }
+
+        let now = self.grower.clock.now();
+        let progress = self.grower.progress.lock().unwrap();
+        if let Some(reason) = progress.as_ref().and_then(|progress| progress.reached(now)) {
+            return Err(Interrupt::Stop(reason));
+        }
+        drop(progress);
+
+        if session.pause_requested.is_some() {
+            return Err(Interrupt::Pause);
+        }
+
+        // watering and fertilizing needs the crops to stay selected
+        let state = self.grower.machine.lock().unwrap().state().clone();
+        if matches!(state, GrowerState::Watering | GrowerState::Fertilizing)
+            && self.selection.state == CurrentlySelected::None
+        {
+            return Err(Interrupt::Stop(StopReason::SelectionChanged));
+        }
+        Ok(())
+    }
+
+    fn interruptible_sleep(&mut self, dur: Duration) -> Result<(), Interrupt> {
+        const SLEEP_TICK: Duration = Duration::from_millis(100);
+        let deadline = self.grower.clock.now() + dur;
+        loop {
+            let now = self.grower.clock.now();
+            if now >= deadline {
+                return Ok(());
+            }
+            self.grower
+                .clock
+                .sleep(std::cmp::min(SLEEP_TICK, deadline - now));
+            self.poll_commands();
+            self.check_idle();
+            self.check_display();
+            self.check_watchdog();
+            self.can_continue()?;
+        }
+    }
 }
 
-fn send_keypress(key: u16) {
-    let mut ip = INPUT {
-        type_: INPUT_KEYBOARD,
-        u: unsafe { MaybeUninit::<INPUT_u>::zeroed().assume_init() },
-    };
+/// When any of the growers last pressed a key, so the bots playing other
+/// clients don't take it for the user's input
+fn last_bot_input() -> Option<Instant> {
+    lock_ignore_poison(&GROWERS)
+        .iter()
+        .filter_map(Weak::upgrade)
+        .filter_map(|grower| *grower.last_input.lock().unwrap())
+        .max()
+}
 
-    // press
-    unsafe {
-        ip.u.ki_mut().wVk = key; // virtual-key code
-        SendInput(1, &mut ip, core::mem::size_of_val(&ip) as i32);
+/// Hotbar key of the slot, 1-10
+fn slot_key(slot: u8) -> u16 {
+    match slot {
+        10 => 0x30, // 0 key
+        _ => 0x30 + slot as u16,
     }
+}
 
-    // release
-    unsafe {
-        ip.u.ki_mut().dwFlags = KEYEVENTF_KEYUP;
-        SendInput(1, &mut ip, core::mem::size_of_val(&ip) as i32);
-    };
+fn needs_action(sel: CurrentlySelected) -> bool {
+    use CurrentlySelected as C;
+    matches!(sel, C::Thisty | C::Hungry | C::Ripe)
 }
 
-fn set_hwnd_focus(hwnd: HWND) -> HWND {
-    let prev = unsafe { GetForegroundWindow() };
-    unsafe { SetForegroundWindow(hwnd) };
-    std::thread::sleep(Duration::from_millis(100));
-    prev
+/// Whether the selection after an action is what we expect to see
+fn action_took_effect(before: CurrentlySelected, after: CurrentlySelected) -> bool {
+    use CurrentlySelected as C;
+    match before {
+        // watered or fertilized crops go back to growing (or need the other thing next)
+        C::Thisty | C::Hungry => after != before,
+        // harvested crops are gone
+        C::Ripe => after == C::None,
+        C::None | C::Growing => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::geometry::{Anchor, WindowRegion};
+    use crate::idle::FakeInputActivity;
+    use crate::profile::{CropPlan, Profile, ReplantSlot};
+    use std::sync::OnceLock;
+
+    use CurrentlySelected as C;
+
+    const USE: u16 = 0x55;
+    const NEXT: u16 = 0x59;
+    /// how often the fake screen is read
+    const READ_INTERVAL: Duration = Duration::from_millis(250);
+
+    fn window() -> HWND {
+        0x1000 as HWND
+    }
+
+    fn plot(state: CurrentlySelected) -> Selection {
+        Selection {
+            state,
+            crop: "Cotton".to_string(),
+        }
+    }
+
+    /// Garden of the fake game, changed by the keys sent to it
+    #[derive(Debug)]
+    struct FakeGame {
+        plots: Vec<Selection>,
+        cursor: usize,
+        pressed: Vec<u16>,
+        /// the game doesn't react to keys sent with that method
+        ignores: Option<InputMethod>,
+        window_open: bool,
+    }
+
+    impl FakeGame {
+        fn press(&mut self, key: u16, method: InputMethod) {
+            self.pressed.push(key);
+            if self.ignores == Some(method) {
+                return;
+            }
+            let selected = &mut self.plots[self.cursor];
+            match key {
+                USE => match selected.state {
+                    C::Thisty | C::Hungry => selected.state = C::Growing,
+                    C::Ripe => *selected = Selection::default(),
+                    C::None | C::Growing => {}
+                },
+                NEXT => self.cursor = (self.cursor + 1) % self.plots.len(),
+                // seeds go to any empty plot
+                0x30..=0x39 => {
+                    if let Some(empty) = self.plots.iter_mut().find(|p| p.state == C::None) {
+                        *empty = plot(C::Growing);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        fn presses(&self, key: u16) -> usize {
+            self.pressed.iter().filter(|&&k| k == key).count()
+        }
+    }
+
+    #[derive(Debug)]
+    struct FakeInput {
+        game: Arc<Mutex<FakeGame>>,
+        method: InputMethod,
+    }
+
+    impl KeyInput for FakeInput {
+        fn window(&self) -> HWND {
+            window()
+        }
+
+        fn method(&self) -> InputMethod {
+            self.method
+        }
+
+        fn begin(&mut self) {}
+
+        fn press(&mut self, key: u16) {
+            self.game.lock().unwrap().press(key, self.method);
+        }
+
+        fn end(&mut self) {}
+
+        fn fall_back(&mut self) {
+            self.method = InputMethod::Foreground;
+        }
+    }
+
+    #[derive(Debug)]
+    struct FakeDesktop(Arc<Mutex<FakeGame>>);
+
+    impl Desktop for FakeDesktop {
+        fn find(&self, _query: &WindowQuery) -> Option<HWND> {
+            self.0.lock().unwrap().window_open.then(window)
+        }
+
+        fn is_alive(&self, _hwnd: HWND) -> bool {
+            self.0.lock().unwrap().window_open
+        }
+
+        fn is_minimized(&self, _hwnd: HWND) -> bool {
+            false
+        }
+
+        fn input(
+            &self,
+            _hwnd: HWND,
+            method: InputMethod,
+            _clock: Arc<dyn Clock>,
+        ) -> Box<dyn KeyInput> {
+            Box::new(FakeInput {
+                game: self.0.clone(),
+                method,
+            })
+        }
+    }
+
+    /// Reads the selected plot of the fake game every so often, the same way
+    /// the GUI reads the real screen
+    #[derive(Debug)]
+    struct ScreenClock {
+        clock: FakeClock,
+        game: Arc<Mutex<FakeGame>>,
+        grower: OnceLock<Weak<Grower>>,
+        next_read: Mutex<Instant>,
+        last_read: Mutex<Selection>,
+    }
+
+    impl ScreenClock {
+        fn new(game: Arc<Mutex<FakeGame>>) -> Self {
+            let clock = FakeClock::new();
+            let now = clock.now();
+            Self {
+                clock,
+                game,
+                grower: OnceLock::new(),
+                next_read: Mutex::new(now),
+                last_read: Mutex::new(Selection::default()),
+            }
+        }
+
+        fn read(&self) {
+            let Some(grower) = self.grower.get().and_then(Weak::upgrade) else {
+                return;
+            };
+            let sel = {
+                let game = self.game.lock().unwrap();
+                game.plots[game.cursor].clone()
+            };
+            let mut last_read = self.last_read.lock().unwrap();
+            if *last_read != sel {
+                *last_read = sel.clone();
+                grower.selection_changed(sel);
+            }
+            grower.scanned();
+        }
+    }
+
+    impl Clock for ScreenClock {
+        fn now(&self) -> Instant {
+            self.clock.now()
+        }
+
+        fn sleep(&self, dur: Duration) {
+            self.clock.advance(dur);
+            let now = self.clock.now();
+            loop {
+                let mut next_read = self.next_read.lock().unwrap();
+                if *next_read > now {
+                    break;
+                }
+                *next_read += READ_INTERVAL;
+                drop(next_read);
+                self.read();
+            }
+        }
+    }
+
+    fn profile(num_objects: usize) -> Profile {
+        Profile {
+            select_region: Some(WindowRegion {
+                anchor: Anchor::Offset,
+                x: 0.0,
+                y: 0.0,
+                w: 100.0,
+                h: 20.0,
+            }),
+            num_objects,
+            num_rounds: 10,
+            ..Profile::default()
+        }
+    }
+
+    /// Grower thread with a session started on the fake game, driven step by
+    /// step from the test
+    fn start(plots: Vec<Selection>, profile: Profile) -> (GrowerThread, Arc<Mutex<FakeGame>>) {
+        start_with(
+            FakeGame {
+                plots,
+                cursor: 0,
+                pressed: Vec::new(),
+                ignores: None,
+                window_open: true,
+            },
+            profile,
+        )
+    }
+
+    fn start_with(game: FakeGame, profile: Profile) -> (GrowerThread, Arc<Mutex<FakeGame>>) {
+        let game = Arc::new(Mutex::new(game));
+        let clock = Arc::new(ScreenClock::new(game.clone()));
+        let (grower, rx) = Grower::unstarted(
+            clock.clone(),
+            Arc::new(FakeInputActivity::new(clock.clone())),
+            Arc::new(FakeDesktop(game.clone())),
+            Arc::new(Mutex::new(GrowthStats::default())),
+        );
+        clock.grower.set(Arc::downgrade(&grower)).unwrap();
+
+        let mut thread = GrowerThread::new(grower, rx);
+        let config = GrowerConfig::try_from(&profile).unwrap();
+        thread.handle_command(GrowerCommand::Start(Box::new(config)));
+        // let the screen be read
+        assert!(thread.interruptible_sleep(Duration::from_secs(1)).is_ok());
+        (thread, game)
+    }
+
+    fn state(thread: &GrowerThread) -> GrowerState {
+        thread.grower.machine.lock().unwrap().state().clone()
+    }
+
+    fn step(thread: &mut GrowerThread) {
+        let result = thread.ensure_input().and_then(|_| thread.step());
+        assert!(result.is_ok(), "step in {} failed", state(thread));
+    }
+
+    fn all_plots(game: &Mutex<FakeGame>, state: CurrentlySelected) -> bool {
+        game.lock().unwrap().plots.iter().all(|p| p.state == state)
+    }
+
+    #[test]
+    fn waits_while_nothing_needs_action() {
+        let (mut thread, game) = start(vec![plot(C::Growing); 3], profile(3));
+        let before = thread.grower.clock.now();
+
+        step(&mut thread);
+
+        assert_eq!(state(&thread), GrowerState::Waiting);
+        assert!(game.lock().unwrap().pressed.is_empty());
+        assert!(thread.grower.clock.now() - before >= Duration::from_secs(5));
+    }
+
+    #[test]
+    fn waters_all_plots() {
+        let (mut thread, game) = start(vec![plot(C::Thisty); 3], profile(3));
+
+        step(&mut thread);
+        assert_eq!(state(&thread), GrowerState::Watering);
+        step(&mut thread);
+
+        assert_eq!(state(&thread), GrowerState::Waiting);
+        assert!(all_plots(&game, C::Growing));
+        let game = game.lock().unwrap();
+        assert_eq!((game.presses(USE), game.presses(NEXT)), (6, 3));
+    }
+
+    #[test]
+    fn fertilizes_hungry_plots() {
+        let (mut thread, game) = start(vec![plot(C::Hungry); 2], profile(2));
+
+        step(&mut thread);
+        assert_eq!(state(&thread), GrowerState::Fertilizing);
+        step(&mut thread);
+
+        assert_eq!(state(&thread), GrowerState::Waiting);
+        assert!(all_plots(&game, C::Growing));
+    }
+
+    #[test]
+    fn leaves_hungry_plots_the_crop_plan_does_not_fertilize() {
+        let mut profile = profile(2);
+        profile.crops.insert(
+            "Cotton".to_string(),
+            CropPlan {
+                fertilize: false,
+                ..CropPlan::default()
+            },
+        );
+        let (mut thread, game) = start(vec![plot(C::Hungry); 2], profile);
+
+        step(&mut thread);
+
+        assert_eq!(state(&thread), GrowerState::Waiting);
+        assert!(game.lock().unwrap().pressed.is_empty());
+    }
+
+    #[test]
+    fn harvests_and_replants() {
+        let (mut thread, game) = start(vec![plot(C::Ripe); 2], profile(2));
+
+        step(&mut thread);
+        assert_eq!(state(&thread), GrowerState::Harvesting);
+        step(&mut thread);
+        assert_eq!(state(&thread), GrowerState::Replanting);
+        assert!(all_plots(&game, C::None));
+        step(&mut thread);
+
+        assert_eq!(state(&thread), GrowerState::Waiting);
+        assert!(all_plots(&game, C::Growing));
+        let progress = thread.grower.progress.lock().unwrap();
+        assert_eq!(progress.as_ref().unwrap().rounds, 1);
+    }
+
+    #[test]
+    fn pause_while_harvesting_still_replants() {
+        let (mut thread, game) = start(vec![plot(C::Ripe); 2], profile(2));
+
+        step(&mut thread);
+        assert_eq!(state(&thread), GrowerState::Harvesting);
+        thread.handle_command(GrowerCommand::Pause);
+        assert!(matches!(thread.step(), Err(Interrupt::Pause)));
+        // the first plot is harvested already
+        assert_eq!(game.lock().unwrap().plots[0].state, C::None);
+
+        thread.grower.resume();
+        assert!(thread.wait_while_paused().is_ok());
+        assert_eq!(state(&thread), GrowerState::Harvesting);
+        step(&mut thread);
+        assert_eq!(state(&thread), GrowerState::Replanting);
+        step(&mut thread);
+
+        assert_eq!(state(&thread), GrowerState::Waiting);
+        assert!(all_plots(&game, C::Growing));
+    }
+
+    #[test]
+    fn paused_from_the_request_until_resumed_on_its_own() {
+        let (mut thread, _game) = start(vec![plot(C::Growing); 2], profile(2));
+
+        thread.grower.pause();
+        assert!(thread.grower.is_paused());
+        assert!(matches!(thread.step(), Err(Interrupt::Pause)));
+
+        // the same as the idle or display check lifting their pause
+        thread.handle_command(GrowerCommand::Resume);
+        assert!(thread.wait_while_paused().is_ok());
+        assert!(!thread.grower.is_paused());
+        assert_eq!(state(&thread), GrowerState::Waiting);
+    }
+
+    #[test]
+    fn gives_up_when_the_game_ignores_the_keys() {
+        let game = FakeGame {
+            plots: vec![plot(C::Thisty); 2],
+            cursor: 0,
+            pressed: Vec::new(),
+            ignores: Some(InputMethod::Foreground),
+            window_open: true,
+        };
+        let (mut thread, game) = start_with(game, profile(2));
+
+        step(&mut thread);
+        let result = thread.step();
+
+        assert!(matches!(
+            result,
+            Err(Interrupt::Stop(StopReason::ActionNotConfirmed(C::Thisty)))
+        ));
+        // two keypresses per plot, on every attempt
+        let attempts = thread.config().action_retries + 1;
+        assert_eq!(game.lock().unwrap().presses(USE), 2 * 2 * attempts);
+    }
+
+    #[test]
+    fn falls_back_to_foreground_when_background_keys_are_ignored() {
+        let game = FakeGame {
+            plots: vec![plot(C::Thisty); 2],
+            cursor: 0,
+            pressed: Vec::new(),
+            ignores: Some(InputMethod::Background),
+            window_open: true,
+        };
+        let mut profile = profile(2);
+        profile.input_method = InputMethod::Background;
+        let (mut thread, game) = start_with(game, profile);
+
+        step(&mut thread);
+        step(&mut thread);
+
+        assert_eq!(state(&thread), GrowerState::Waiting);
+        assert!(all_plots(&game, C::Growing));
+        assert_eq!(thread.input().method(), InputMethod::Foreground);
+    }
+
+    #[test]
+    fn needs_the_game_window() {
+        let (mut thread, game) = start(vec![plot(C::Thisty); 2], profile(2));
+        game.lock().unwrap().window_open = false;
+
+        assert!(matches!(
+            thread.ensure_input(),
+            Err(Interrupt::Stop(StopReason::WindowNotFound))
+        ));
+    }
+
+    #[test]
+    fn finishes_once_the_goal_is_reached() {
+        let mut profile = profile(2);
+        profile.num_rounds = 1;
+        let (mut thread, game) = start(vec![plot(C::Ripe); 2], profile);
+
+        assert_eq!(thread.run_session(), StopReason::FinishedAllRounds);
+        assert!(all_plots(&game, C::None));
+    }
+
+    #[test]
+    fn start_request_while_busy_runs_after_the_session() {
+        let (mut thread, _game) = start(vec![plot(C::Growing); 2], profile(2));
+        let grower = thread.grower.clone();
+        grower.progress.lock().unwrap().as_mut().unwrap().rounds = 5;
+
+        grower.start(GrowerConfig::try_from(&profile(2)).unwrap());
+        thread.session_mut().stop_requested = Some(StopReason::UserRequest);
+        // the thread exits once it's done with everything sent so far
+        grower.tx.lock().unwrap().take();
+        thread.run();
+
+        assert!(!grower.is_running());
+        assert_eq!(grower.progress.lock().unwrap().as_ref().unwrap().rounds, 0);
+    }
+
+    #[test]
+    fn stop_cancels_the_queued_start() {
+        let (mut thread, _game) = start(vec![plot(C::Growing); 2], profile(2));
+
+        thread
+            .grower
+            .start(GrowerConfig::try_from(&profile(2)).unwrap());
+        thread.poll_commands();
+        assert!(thread.queued.is_some());
+        thread.grower.stop();
+        thread.poll_commands();
+
+        assert!(thread.queued.is_none());
+        assert!(!thread.grower.starting.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn per_plot_acts_only_on_plots_that_need_it() {
+        let mut profile = profile(3);
+        profile.per_plot = true;
+        let plots = vec![plot(C::Thisty), plot(C::Growing), plot(C::Thisty)];
+        let (mut thread, game) = start(plots, profile);
+
+        for _ in 0..5 {
+            step(&mut thread);
+        }
+
+        assert_eq!(state(&thread), GrowerState::Waiting);
+        assert_eq!(thread.session().plots_left, 0);
+        assert!(all_plots(&game, C::Growing));
+        let game = game.lock().unwrap();
+        assert_eq!((game.presses(USE), game.presses(NEXT)), (4, 3));
+    }
+
+    #[test]
+    fn per_plot_replants_the_harvested_plot() {
+        let mut profile = profile(2);
+        profile.per_plot = true;
+        let (mut thread, game) = start(vec![plot(C::Ripe), plot(C::Growing)], profile);
+
+        step(&mut thread);
+        assert_eq!(state(&thread), GrowerState::Harvesting);
+        step(&mut thread);
+        assert_eq!(state(&thread), GrowerState::Replanting);
+        step(&mut thread);
+
+        assert_eq!(state(&thread), GrowerState::Waiting);
+        assert!(all_plots(&game, C::Growing));
+        let progress = thread.grower.progress.lock().unwrap();
+        assert_eq!(progress.as_ref().unwrap().plants, 1);
+    }
+
+    #[test]
+    fn counts_down_only_the_seeds_that_were_planted() {
+        let mut profile = profile(2);
+        profile.per_plot = true;
+        profile.replant_slots = vec![
+            ReplantSlot {
+                slot: 1,
+                seeds: Some(5),
+            },
+            ReplantSlot {
+                slot: 2,
+                seeds: Some(5),
+            },
+        ];
+        let (mut thread, game) = start(vec![plot(C::Ripe), plot(C::Growing)], profile);
+
+        step(&mut thread);
+        step(&mut thread);
+        assert_eq!(state(&thread), GrowerState::Replanting);
+        step(&mut thread);
+        // both slots were pressed, but only one plot was empty
+        assert_eq!(game.lock().unwrap().presses(slot_key(1)), 1);
+        assert_eq!(game.lock().unwrap().presses(slot_key(2)), 1);
+        // not seen planted yet
+        assert_eq!(thread.session().stock[&1], 5);
+
+        // around the garden, back to the replanted plot
+        step(&mut thread);
+        step(&mut thread);
+        assert_eq!(state(&thread), GrowerState::Waiting);
+        let stock = &thread.session().stock;
+        assert_eq!((stock[&1], stock[&2]), (4, 5));
+    }
 }
@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::screenshot::Rectangle;
+
+/// Number of slots in the hotbar capture region, all of the same width
+pub const NUM_SLOTS: u8 = 10;
+
+/// What to do when a hotbar stack runs low
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StockAction {
+    /// only highlight it in the window
+    #[default]
+    Alert,
+    Pause,
+    Stop,
+}
+
+/// Hotbar slot whose stack count is read from the screen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotbarSlot {
+    /// 1-10
+    pub slot: u8,
+    /// shown in the window, e.g. "Water"
+    pub name: String,
+    /// the stack is low below `min + min_per_plot * number of plots`
+    #[serde(default)]
+    pub min: usize,
+    #[serde(default)]
+    pub min_per_plot: usize,
+    #[serde(default)]
+    pub action: StockAction,
+}
+
+impl HotbarSlot {
+    pub fn is_low(&self, count: usize, num_objects: usize) -> bool {
+        count < self.min + self.min_per_plot * num_objects
+    }
+}
+
+/// Part of the hotbar region with the stack counts of all slots. The counts
+/// are drawn in the bottom half of the slots.
+pub fn counts_rect(hotbar: Rectangle<f64>) -> Rectangle<f64> {
+    Rectangle {
+        y: hotbar.y + hotbar.h / 2.0,
+        h: hotbar.h / 2.0,
+        ..hotbar
+    }
+}
+
+/// Stack counts per slot from the tesseract TSV output for a capture of
+/// `captured`, the on-screen part of [`counts_rect`]. Each number belongs to
+/// the slot under its middle.
+pub fn parse_counts(
+    tsv: &str,
+    counts: Rectangle<f64>,
+    captured: Rectangle<u32>,
+) -> BTreeMap<u8, usize> {
+    let slot_w = counts.w / NUM_SLOTS as f64;
+    let mut slots = BTreeMap::new();
+    for line in tsv.lines() {
+        // level, page, block, paragraph, line, word, left, top, width, height, conf, text
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [level, _, _, _, _, _, left, _, width, _, _, text] = fields[..] else {
+            continue;
+        };
+        // only words have any text
+        let (Ok(5), Ok(left), Ok(width), Ok(count)) = (
+            level.parse::<u32>(),
+            left.parse::<u32>(),
+            width.parse::<u32>(),
+            text.trim().parse::<usize>(),
+        ) else {
+            continue;
+        };
+        let middle = captured.x as f64 + left as f64 + width as f64 / 2.0;
+        let slot = ((middle - counts.x) / slot_w).floor();
+        if (0.0..NUM_SLOTS as f64).contains(&slot) {
+            slots.entry(slot as u8 + 1).or_insert(count);
+        }
+    }
+    slots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10 slots of 40x40 pixels at (100, 500)
+    fn counts() -> Rectangle<f64> {
+        counts_rect(Rectangle {
+            x: 100.0,
+            y: 500.0,
+            w: 400.0,
+            h: 40.0,
+        })
+    }
+
+    fn word(left: u32, width: u32, text: &str) -> String {
+        format!("5\t1\t1\t1\t1\t1\t{left}\t2\t{width}\t12\t91.5\t{text}")
+    }
+
+    #[test]
+    fn counts_are_in_the_bottom_half() {
+        let rect = counts();
+        assert_eq!((rect.y, rect.h), (520.0, 20.0));
+        assert_eq!((rect.x, rect.w), (100.0, 400.0));
+    }
+
+    #[test]
+    fn numbers_go_to_the_slot_under_their_middle() {
+        let tsv = [
+            "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext".to_string(),
+            "1\t1\t0\t0\t0\t0\t0\t0\t400\t20\t-1\t".to_string(),
+            word(20, 16, "12"),
+            // close to the right edge of slot 3
+            word(110, 8, "7"),
+            word(370, 24, "250"),
+        ]
+        .join("\n");
+        let captured = Rectangle {
+            x: 100,
+            y: 520,
+            w: 400,
+            h: 20,
+        };
+        let slots = parse_counts(&tsv, counts(), captured);
+        assert_eq!(slots, BTreeMap::from([(1, 12), (3, 7), (10, 250)]));
+    }
+
+    #[test]
+    fn offscreen_part_of_the_hotbar_keeps_the_slots_in_place() {
+        // the first 60 pixels of the hotbar are off the screen
+        let captured = Rectangle {
+            x: 160,
+            y: 520,
+            w: 340,
+            h: 20,
+        };
+        let slots = parse_counts(&word(0, 10, "30"), counts(), captured);
+        assert_eq!(slots, BTreeMap::from([(2, 30)]));
+    }
+
+    #[test]
+    fn ignores_anything_but_numbers() {
+        let captured = Rectangle {
+            x: 100,
+            y: 520,
+            w: 400,
+            h: 20,
+        };
+        let tsv = [word(20, 16, ""), word(60, 16, "x"), "garbage".to_string()].join("\n");
+        assert!(parse_counts(&tsv, counts(), captured).is_empty());
+    }
+}
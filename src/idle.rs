@@ -0,0 +1,218 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(windows)]
+use winapi::um::sysinfoapi::GetTickCount;
+#[cfg(windows)]
+use winapi::um::winuser::{GetLastInputInfo, LASTINPUTINFO};
+
+/// Input within that time of our own keypress is assumed to be ours
+const OWN_INPUT_GRACE: Duration = Duration::from_millis(500);
+
+/// Source of the user's keyboard and mouse activity
+pub trait InputActivity: Send + Sync + Debug {
+    /// Time since the last keyboard or mouse input, None if unknown
+    fn idle_time(&self) -> Option<Duration>;
+}
+
+/// Any input on this system, including the keys we send ourselves
+#[cfg(windows)]
+#[derive(Debug)]
+pub struct SystemInputActivity;
+
+#[cfg(windows)]
+impl InputActivity for SystemInputActivity {
+    fn idle_time(&self) -> Option<Duration> {
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return None;
+        }
+
+        let now = unsafe { GetTickCount() };
+        Some(Duration::from_millis(now.wrapping_sub(info.dwTime) as u64))
+    }
+}
+
+/// Input that only happens when told to, timed by the given clock
+#[cfg(test)]
+#[derive(Debug)]
+pub struct FakeInputActivity {
+    clock: Arc<dyn crate::clock::Clock>,
+    last_input: std::sync::Mutex<Option<Instant>>,
+}
+
+#[cfg(test)]
+impl FakeInputActivity {
+    pub fn new(clock: Arc<dyn crate::clock::Clock>) -> Self {
+        Self {
+            clock,
+            last_input: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Pretend a key was pressed just now
+    pub fn input(&self) {
+        *self.last_input.lock().unwrap() = Some(self.clock.now());
+    }
+}
+
+#[cfg(test)]
+impl InputActivity for FakeInputActivity {
+    fn idle_time(&self) -> Option<Duration> {
+        let last_input = (*self.last_input.lock().unwrap())?;
+        Some(self.clock.now().saturating_duration_since(last_input))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IdlePolicy {
+    /// pause if the user did anything that recently
+    pub active_window: Duration,
+    /// resume once the user did nothing for that long
+    pub quiet_period: Duration,
+}
+
+/// Tells whether the user is busy with the keyboard or mouse, ignoring the
+/// input sent by the bot itself
+#[derive(Debug)]
+pub struct IdleWatch {
+    policy: IdlePolicy,
+    source: Arc<dyn InputActivity>,
+    /// anything before that (e.g. clicking Start) doesn't count
+    started: Instant,
+    own_input: Option<Instant>,
+    last_user_input: Option<Instant>,
+}
+
+impl IdleWatch {
+    pub fn new(policy: IdlePolicy, source: Arc<dyn InputActivity>, now: Instant) -> Self {
+        Self {
+            policy,
+            source,
+            started: now,
+            own_input: None,
+            last_user_input: None,
+        }
+    }
+
+    /// The bot just sent some input
+    pub fn own_input(&mut self, at: Instant) {
+        self.own_input = Some(at);
+    }
+
+    pub fn user_active(&mut self, now: Instant) -> bool {
+        self.update(now);
+        self.last_user_input
+            .is_some_and(|at| now.saturating_duration_since(at) < self.policy.active_window)
+    }
+
+    pub fn user_quiet(&mut self, now: Instant) -> bool {
+        self.update(now);
+        self.last_user_input
+            .is_none_or(|at| now.saturating_duration_since(at) >= self.policy.quiet_period)
+    }
+
+    fn update(&mut self, now: Instant) {
+        let Some(last_input) = self
+            .source
+            .idle_time()
+            .and_then(|idle| now.checked_sub(idle))
+        else {
+            return;
+        };
+        if last_input <= self.started {
+            return;
+        }
+        let ours = self.own_input.is_some_and(|own| {
+            let diff = std::cmp::max(own, last_input) - std::cmp::min(own, last_input);
+            diff <= OWN_INPUT_GRACE
+        });
+        if !ours && self.last_user_input.is_none_or(|at| last_input > at) {
+            self.last_user_input = Some(last_input);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, FakeClock};
+
+    const POLICY: IdlePolicy = IdlePolicy {
+        active_window: Duration::from_secs(2),
+        quiet_period: Duration::from_secs(10),
+    };
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn watch() -> (IdleWatch, Arc<FakeClock>, Arc<FakeInputActivity>) {
+        let clock = Arc::new(FakeClock::new());
+        let activity = Arc::new(FakeInputActivity::new(clock.clone()));
+        let watch = IdleWatch::new(POLICY, activity.clone(), clock.now());
+        (watch, clock, activity)
+    }
+
+    #[test]
+    fn nobody_is_active_without_input() {
+        let (mut watch, clock, _) = watch();
+        clock.advance(secs(5));
+        assert!(!watch.user_active(clock.now()));
+        assert!(watch.user_quiet(clock.now()));
+    }
+
+    #[test]
+    fn pauses_when_the_user_is_active() {
+        let (mut watch, clock, activity) = watch();
+        clock.advance(secs(5));
+        activity.input();
+        clock.advance(secs(1));
+        assert!(watch.user_active(clock.now()));
+        assert!(!watch.user_quiet(clock.now()));
+    }
+
+    #[test]
+    fn resumes_after_the_quiet_period() {
+        let (mut watch, clock, activity) = watch();
+        clock.advance(secs(5));
+        activity.input();
+
+        // no longer active, but not quiet for long enough yet
+        clock.advance(secs(3));
+        assert!(!watch.user_active(clock.now()));
+        assert!(!watch.user_quiet(clock.now()));
+
+        clock.advance(secs(7));
+        assert!(watch.user_quiet(clock.now()));
+    }
+
+    #[test]
+    fn input_before_the_start_does_not_count() {
+        let clock = Arc::new(FakeClock::new());
+        let activity = Arc::new(FakeInputActivity::new(clock.clone()));
+        activity.input();
+        clock.advance(secs(1));
+        let mut watch = IdleWatch::new(POLICY, activity, clock.now());
+        assert!(!watch.user_active(clock.now()));
+    }
+
+    #[test]
+    fn ignores_own_input_within_the_grace_period() {
+        let (mut watch, clock, activity) = watch();
+        clock.advance(secs(5));
+        watch.own_input(clock.now());
+        clock.advance(OWN_INPUT_GRACE / 2);
+        activity.input();
+        assert!(!watch.user_active(clock.now()));
+
+        // the user pressed something after the bot did
+        clock.advance(secs(1));
+        activity.input();
+        assert!(watch.user_active(clock.now()));
+    }
+}
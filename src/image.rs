@@ -0,0 +1,417 @@
+use crate::screenshot::Rectangle;
+
+/// Layout of a single pixel
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PixelFormat {
+    /// what GDI gives us
+    Bgr,
+    /// what OpenGL textures take
+    Rgb,
+    Rgba,
+    Gray,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Bgr | Self::Rgb => 3,
+            Self::Rgba => 4,
+            Self::Gray => 1,
+        }
+    }
+
+    /// (r, g, b, a) of the pixel in this format
+    fn rgba(self, pixel: &[u8]) -> [u8; 4] {
+        match self {
+            Self::Bgr => [pixel[2], pixel[1], pixel[0], u8::MAX],
+            Self::Rgb => [pixel[0], pixel[1], pixel[2], u8::MAX],
+            Self::Rgba => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            Self::Gray => [pixel[0], pixel[0], pixel[0], u8::MAX],
+        }
+    }
+
+    /// Append the (r, g, b, a) pixel in this format
+    fn push(self, [r, g, b, a]: [u8; 4], out: &mut Vec<u8>) {
+        match self {
+            Self::Bgr => out.extend_from_slice(&[b, g, r]),
+            Self::Rgb => out.extend_from_slice(&[r, g, b]),
+            Self::Rgba => out.extend_from_slice(&[r, g, b, a]),
+            // ITU-R BT.601 luma
+            Self::Gray => {
+                let luma = (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000;
+                out.push(luma as u8);
+            }
+        }
+    }
+}
+
+/// Order of the rows in memory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    TopDown,
+    /// as in BMP files and OpenGL textures
+    BottomUp,
+}
+
+/// Pixel buffer that knows its own layout
+#[derive(Debug, Clone)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    /// bytes per row, including any padding
+    stride: usize,
+    format: PixelFormat,
+    orientation: Orientation,
+    data: Vec<u8>,
+}
+
+impl Image {
+    /// Black image without any row padding
+    pub fn new(width: u32, height: u32, format: PixelFormat) -> Self {
+        let stride = width as usize * format.bytes_per_pixel();
+        Self {
+            width,
+            height,
+            stride,
+            format,
+            orientation: Orientation::TopDown,
+            data: vec![0; stride * height as usize],
+        }
+    }
+
+    /// Wrap existing pixel data. None if it's too short for the given
+    /// layout, or the rows overlap.
+    pub fn from_raw(
+        width: u32,
+        height: u32,
+        stride: usize,
+        format: PixelFormat,
+        orientation: Orientation,
+        data: Vec<u8>,
+    ) -> Option<Self> {
+        if stride < width as usize * format.bytes_per_pixel()
+            || data.len() < stride * height as usize
+        {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            stride,
+            format,
+            orientation,
+            data,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Size of the pixel data in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Pixel data in the image's own layout
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// The whole image
+    pub fn view(&self) -> ImageView<'_> {
+        ImageView {
+            image: self,
+            rect: Rectangle {
+                x: 0,
+                y: 0,
+                w: self.width,
+                h: self.height,
+            },
+        }
+    }
+
+    /// Part of the image, None if it doesn't fit inside
+    pub fn sub_image(&self, rect: Rectangle<u32>) -> Option<ImageView<'_>> {
+        self.view().sub_image(rect)
+    }
+
+    /// Pixels of the row `y`, counting from the top
+    fn row(&self, y: u32) -> &[u8] {
+        let row = match self.orientation {
+            Orientation::TopDown => y,
+            Orientation::BottomUp => self.height - 1 - y,
+        };
+        let start = row as usize * self.stride;
+        &self.data[start..start + self.width as usize * self.format.bytes_per_pixel()]
+    }
+}
+
+/// Borrowed rectangular part of an image
+#[derive(Debug, Clone, Copy)]
+pub struct ImageView<'a> {
+    image: &'a Image,
+    /// always inside the image
+    rect: Rectangle<u32>,
+}
+
+impl<'a> ImageView<'a> {
+    pub fn width(&self) -> u32 {
+        self.rect.w
+    }
+
+    pub fn height(&self) -> u32 {
+        self.rect.h
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.image.format
+    }
+
+    /// Part of this view, in its coordinates. None if it doesn't fit inside.
+    pub fn sub_image(&self, rect: Rectangle<u32>) -> Option<ImageView<'a>> {
+        let fits_x = rect.x.checked_add(rect.w).is_some_and(|x| x <= self.rect.w);
+        let fits_y = rect.y.checked_add(rect.h).is_some_and(|y| y <= self.rect.h);
+        if !fits_x || !fits_y {
+            return None;
+        }
+        Some(ImageView {
+            image: self.image,
+            rect: Rectangle {
+                x: self.rect.x + rect.x,
+                y: self.rect.y + rect.y,
+                ..rect
+            },
+        })
+    }
+
+    /// Pixels of the row `y`, counting from the top of the view
+    pub fn row(&self, y: u32) -> &'a [u8] {
+        assert!(y < self.rect.h, "row {y} outside of the image");
+        let bpp = self.format().bytes_per_pixel();
+        let row = self.image.row(self.rect.y + y);
+        let start = self.rect.x as usize * bpp;
+        &row[start..start + self.rect.w as usize * bpp]
+    }
+
+    /// All rows, from the top
+    pub fn rows(&self) -> impl Iterator<Item = &'a [u8]> {
+        let view = *self;
+        (0..self.rect.h).map(move |y| view.row(y))
+    }
+
+    /// Copy of the view in the given layout, without row padding
+    pub fn convert(&self, format: PixelFormat, orientation: Orientation) -> Image {
+        let stride = self.rect.w as usize * format.bytes_per_pixel();
+        let mut data = Vec::with_capacity(stride * self.rect.h as usize);
+        let src = self.format();
+        let mut push_row = |row: &[u8]| {
+            if src == format {
+                data.extend_from_slice(row);
+                return;
+            }
+            for pixel in row.chunks_exact(src.bytes_per_pixel()) {
+                format.push(src.rgba(pixel), &mut data);
+            }
+        };
+        match orientation {
+            Orientation::TopDown => self.rows().for_each(&mut push_row),
+            Orientation::BottomUp => (0..self.rect.h).rev().for_each(|y| push_row(self.row(y))),
+        }
+        Image {
+            width: self.rect.w,
+            height: self.rect.h,
+            stride,
+            format,
+            orientation,
+            data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: u32, y: u32, w: u32, h: u32) -> Rectangle<u32> {
+        Rectangle { x, y, w, h }
+    }
+
+    /// 2x2 BGR image with 2 bytes of padding after each row, stored
+    /// bottom-up the way GDI does it. The pixels are, from the top left:
+    /// red, green / blue, white.
+    fn gdi_image() -> Image {
+        let data = vec![
+            // bottom row: blue, white
+            255, 0, 0, 255, 255, 255, 0xEE, 0xEE, //
+            // top row: red, green
+            0, 0, 255, 0, 255, 0, 0xEE, 0xEE,
+        ];
+        Image::from_raw(2, 2, 8, PixelFormat::Bgr, Orientation::BottomUp, data).unwrap()
+    }
+
+    #[test]
+    fn rows_skip_the_padding_and_start_at_the_top() {
+        let image = gdi_image();
+        let rows: Vec<&[u8]> = image.view().rows().collect();
+        assert_eq!(
+            rows,
+            [&[0, 0, 255, 0, 255, 0][..], &[255, 0, 0, 255, 255, 255]]
+        );
+    }
+
+    #[test]
+    fn converts_bgr_to_rgb() {
+        let rgb = gdi_image()
+            .view()
+            .convert(PixelFormat::Rgb, Orientation::TopDown);
+        assert_eq!(
+            rgb.into_data(),
+            [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255]
+        );
+    }
+
+    /// Top-down 2x1 image with no padding
+    fn image(format: PixelFormat, data: Vec<u8>) -> Image {
+        let stride = data.len();
+        Image::from_raw(2, 1, stride, format, Orientation::TopDown, data).unwrap()
+    }
+
+    fn convert(image: &Image, format: PixelFormat) -> Image {
+        image.view().convert(format, Orientation::TopDown)
+    }
+
+    #[test]
+    fn rgb_round_trips_through_rgba() {
+        let rgb = image(PixelFormat::Rgb, vec![10, 20, 30, 200, 150, 100]);
+        let rgba = convert(&rgb, PixelFormat::Rgba);
+        assert_eq!(
+            rgba.view().row(0),
+            [10, 20, 30, u8::MAX, 200, 150, 100, u8::MAX]
+        );
+        let back = convert(&rgba, PixelFormat::Rgb);
+        assert_eq!(back.into_data(), rgb.into_data());
+    }
+
+    #[test]
+    fn bgr_round_trips_through_rgba() {
+        let bgr = gdi_image()
+            .view()
+            .convert(PixelFormat::Bgr, Orientation::TopDown);
+        let rgba = convert(&bgr, PixelFormat::Rgba);
+        assert_eq!(rgba.view().row(0), [255, 0, 0, 255, 0, 255, 0, 255]);
+        let back = convert(&rgba, PixelFormat::Bgr);
+        assert_eq!(back.into_data(), bgr.into_data());
+    }
+
+    #[test]
+    fn rgba_keeps_its_alpha() {
+        let rgba = Image::from_raw(
+            1,
+            1,
+            4,
+            PixelFormat::Rgba,
+            Orientation::TopDown,
+            vec![1, 2, 3, 4],
+        )
+        .unwrap();
+        assert_eq!(convert(&rgba, PixelFormat::Rgb).into_data(), [1, 2, 3]);
+        assert_eq!(convert(&rgba, PixelFormat::Bgr).into_data(), [3, 2, 1]);
+        // converting to the same format copies it as is
+        assert_eq!(convert(&rgba, PixelFormat::Rgba).into_data(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn gray_round_trips_through_rgb() {
+        let gray = image(PixelFormat::Gray, vec![0, 137]);
+        let rgb = convert(&gray, PixelFormat::Rgb);
+        assert_eq!(rgb.view().row(0), [0, 0, 0, 137, 137, 137]);
+        let back = convert(&rgb, PixelFormat::Gray);
+        assert_eq!(back.into_data(), [0, 137]);
+    }
+
+    #[test]
+    fn colors_turn_to_luma() {
+        let rgb = image(PixelFormat::Rgb, vec![255, 0, 0, 255, 255, 255]);
+        assert_eq!(convert(&rgb, PixelFormat::Gray).into_data(), [76, 255]);
+        let gray = gdi_image()
+            .view()
+            .convert(PixelFormat::Gray, Orientation::TopDown);
+        // red, green / blue, white
+        assert_eq!(gray.into_data(), [76, 149, 29, 255]);
+    }
+
+    #[test]
+    fn converting_to_the_same_format_drops_the_padding() {
+        let image = gdi_image();
+        let bgr = image
+            .view()
+            .convert(PixelFormat::Bgr, Orientation::BottomUp);
+        assert_eq!(bgr.size_bytes(), 12);
+        assert_eq!(
+            bgr.into_data(),
+            [255, 0, 0, 255, 255, 255, 0, 0, 255, 0, 255, 0]
+        );
+    }
+
+    #[test]
+    fn flips_between_top_down_and_bottom_up() {
+        let top_down = gdi_image()
+            .view()
+            .convert(PixelFormat::Bgr, Orientation::TopDown);
+        assert_eq!(top_down.view().row(0), [0, 0, 255, 0, 255, 0]);
+
+        let bottom_up = top_down
+            .view()
+            .convert(PixelFormat::Bgr, Orientation::BottomUp);
+        // the same rows from the top, but stored the other way around
+        assert_eq!(bottom_up.view().row(0), [0, 0, 255, 0, 255, 0]);
+        assert_eq!(&bottom_up.into_data()[..6], [255, 0, 0, 255, 255, 255]);
+    }
+
+    #[test]
+    fn rejects_data_too_short_for_the_layout() {
+        let too_short = vec![0; 15];
+        assert!(
+            Image::from_raw(2, 2, 8, PixelFormat::Bgr, Orientation::TopDown, too_short).is_none()
+        );
+        // rows overlapping each other
+        assert!(
+            Image::from_raw(2, 2, 4, PixelFormat::Bgr, Orientation::TopDown, vec![0; 16]).is_none()
+        );
+    }
+
+    #[test]
+    fn sub_image_of_a_bottom_up_image() {
+        let image = gdi_image();
+        let white = image.sub_image(rect(1, 1, 1, 1)).unwrap();
+        assert_eq!(white.row(0), [255, 255, 255]);
+        let left = image.sub_image(rect(0, 0, 1, 2)).unwrap();
+        assert_eq!(left.rows().collect::<Vec<_>>(), [[0, 0, 255], [255, 0, 0]]);
+    }
+
+    #[test]
+    fn sub_image_of_a_sub_image_is_relative_to_it() {
+        let image = Image::new(10, 10, PixelFormat::Rgb);
+        let inner = image.sub_image(rect(2, 3, 5, 5)).unwrap();
+        let nested = inner.sub_image(rect(1, 1, 4, 4)).unwrap();
+        assert_eq!((nested.width(), nested.height()), (4, 4));
+        assert!(inner.sub_image(rect(1, 1, 5, 4)).is_none());
+    }
+
+    #[test]
+    fn sub_image_must_fit_inside() {
+        let image = Image::new(10, 10, PixelFormat::Bgr);
+        assert!(image.sub_image(rect(0, 0, 10, 10)).is_some());
+        assert!(image.sub_image(rect(10, 0, 0, 10)).is_some());
+        assert!(image.sub_image(rect(5, 5, 6, 1)).is_none());
+        assert!(image.sub_image(rect(0, 9, 1, 2)).is_none());
+        // no overflow on huge offsets
+        assert!(image.sub_image(rect(u32::MAX, 0, 1, 1)).is_none());
+    }
+}
@@ -0,0 +1,190 @@
+#[cfg(windows)]
+use std::mem::MaybeUninit;
+#[cfg(windows)]
+use std::sync::{Arc, Mutex, MutexGuard};
+#[cfg(windows)]
+use std::time::Duration;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
+use winapi::shared::minwindef::{LPARAM, WPARAM};
+#[cfg(windows)]
+use winapi::um::winuser::{
+    GetForegroundWindow, INPUT_u, MapVirtualKeyA, PostMessageA, SendInput, SetForegroundWindow,
+    INPUT, INPUT_KEYBOARD, KEYEVENTF_KEYUP, MAPVK_VK_TO_VSC, WM_KEYDOWN, WM_KEYUP,
+};
+
+#[cfg(windows)]
+use crate::clock::Clock;
+use crate::window::HWND;
+
+/// Held while a bot keeps the focus on its game window, so bots playing other
+/// clients don't steal it in the middle of a key sequence
+#[cfg(windows)]
+static FOREGROUND: Mutex<()> = Mutex::new(());
+
+/// How keypresses are delivered to the game
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, IntoPrimitive, TryFromPrimitive, Serialize, Deserialize,
+)]
+#[repr(u8)]
+pub enum InputMethod {
+    /// bring the game window to the foreground and inject keys system-wide
+    #[default]
+    Foreground,
+    /// post key messages straight to the game window, leaving the focus alone
+    Background,
+}
+
+/// Keys sent to a single game window
+pub trait KeyInput: std::fmt::Debug {
+    fn window(&self) -> HWND;
+    fn method(&self) -> InputMethod;
+    /// Prepare for a sequence of keypresses
+    fn begin(&mut self);
+    fn press(&mut self, key: u16);
+    /// Finish the sequence started with [`Self::begin`]
+    fn end(&mut self);
+    /// Switch to the foreground method for the rest of the session. Used when
+    /// the game doesn't react to messages posted to its window.
+    fn fall_back(&mut self);
+}
+
+/// Keyboard input bound to a single game window
+#[cfg(windows)]
+#[derive(Debug)]
+pub struct GameInput {
+    window: HWND,
+    method: InputMethod,
+    prev_foreground: Option<HWND>,
+    foreground_lock: Option<MutexGuard<'static, ()>>,
+    /// every key pressed so far, so they can be all released on drop
+    used_keys: Vec<u16>,
+    clock: Arc<dyn Clock>,
+}
+
+#[cfg(windows)]
+impl GameInput {
+    pub fn new(window: HWND, method: InputMethod, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            window,
+            method,
+            prev_foreground: None,
+            foreground_lock: None,
+            used_keys: Vec::new(),
+            clock,
+        }
+    }
+
+    /// Make sure no key is left pressed, whatever state we were interrupted in
+    fn release_keys(&self) {
+        for &key in &self.used_keys {
+            match self.method {
+                InputMethod::Foreground => send_key_event(key, KEYEVENTF_KEYUP),
+                InputMethod::Background => {
+                    post_key_message(self.window, WM_KEYUP, key);
+                }
+            }
+        }
+    }
+
+    fn set_hwnd_focus(&self, hwnd: HWND) -> HWND {
+        let prev = unsafe { GetForegroundWindow() };
+        unsafe { SetForegroundWindow(hwnd) };
+        self.clock.sleep(Duration::from_millis(100));
+        prev
+    }
+}
+
+#[cfg(windows)]
+impl KeyInput for GameInput {
+    fn window(&self) -> HWND {
+        self.window
+    }
+
+    fn method(&self) -> InputMethod {
+        self.method
+    }
+
+    fn begin(&mut self) {
+        if self.method == InputMethod::Foreground && self.prev_foreground.is_none() {
+            // wait for any other bot to finish its sequence
+            self.foreground_lock = Some(FOREGROUND.lock().unwrap_or_else(|e| e.into_inner()));
+            self.prev_foreground = Some(self.set_hwnd_focus(self.window));
+        }
+    }
+
+    fn press(&mut self, key: u16) {
+        if !self.used_keys.contains(&key) {
+            self.used_keys.push(key);
+        }
+        if self.method == InputMethod::Background && !post_keypress(self.window, key) {
+            self.fall_back();
+        }
+        if self.method == InputMethod::Foreground {
+            send_keypress(key);
+        }
+    }
+
+    fn end(&mut self) {
+        if let Some(prev) = self.prev_foreground.take() {
+            self.set_hwnd_focus(prev);
+        }
+        self.foreground_lock = None;
+    }
+
+    fn fall_back(&mut self) {
+        if self.method == InputMethod::Foreground {
+            return;
+        }
+        self.method = InputMethod::Foreground;
+        self.begin();
+    }
+}
+
+#[cfg(windows)]
+impl Drop for GameInput {
+    fn drop(&mut self) {
+        self.release_keys();
+        self.end();
+    }
+}
+
+#[cfg(windows)]
+fn send_keypress(key: u16) {
+    send_key_event(key, 0); // press
+    send_key_event(key, KEYEVENTF_KEYUP); // release
+}
+
+#[cfg(windows)]
+fn send_key_event(key: u16, flags: u32) {
+    let mut ip = INPUT {
+        type_: INPUT_KEYBOARD,
+        u: unsafe { MaybeUninit::<INPUT_u>::zeroed().assume_init() },
+    };
+
+    unsafe {
+        ip.u.ki_mut().wVk = key; // virtual-key code
+        ip.u.ki_mut().dwFlags = flags;
+        SendInput(1, &mut ip, core::mem::size_of_val(&ip) as i32);
+    }
+}
+
+#[cfg(windows)]
+fn post_keypress(hwnd: HWND, key: u16) -> bool {
+    post_key_message(hwnd, WM_KEYDOWN, key) && post_key_message(hwnd, WM_KEYUP, key)
+}
+
+#[cfg(windows)]
+fn post_key_message(hwnd: HWND, msg: u32, key: u16) -> bool {
+    let scan_code = unsafe { MapVirtualKeyA(key as u32, MAPVK_VK_TO_VSC) } as LPARAM;
+    // repeat count of 1, then the scan code. Key up additionally sets the
+    // previous key state and transition state bits
+    let mut lparam = 1 | (scan_code << 16);
+    if msg == WM_KEYUP {
+        lparam |= (1 << 30) | (1 << 31);
+    }
+
+    unsafe { PostMessageA(hwnd, msg, key as WPARAM, lparam) != 0 }
+}
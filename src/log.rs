@@ -0,0 +1,31 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether to print what the bot is doing, set with `--verbose`
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+pub fn verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+/// Same as `println!`, but only when started with `--verbose`. Errors go
+/// through [`error!`] instead.
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if $crate::log::verbose() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Something went wrong, printed even without `--verbose`
+macro_rules! error {
+    ($($arg:tt)*) => {
+        println!($($arg)*)
+    };
+}
+
+pub(crate) use {error, log};
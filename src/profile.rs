@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::capture::CaptureSchedule;
+use crate::geometry::{Anchor, WindowRegion};
+use crate::hotbar::HotbarSlot;
+use crate::input::InputMethod;
+use crate::log::error;
+use crate::screenshot::Rectangle;
+use crate::window::WindowQuery;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// How to take care of a specific crop, matched against its name read from the screen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CropPlan {
+    /// hotbar slot with the seeds, 1-10. Without it all slots 1-5 are tried.
+    pub seed_slot: Option<u8>,
+    /// whether to fertilize it when it's hungry
+    pub fertilize: bool,
+    /// waited before every action on this crop, on top of the global one
+    pub extra_delay_secs: usize,
+}
+
+/// Hotbar slot tried when replanting a crop without a seed slot of its own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplantSlot {
+    /// 1-10
+    pub slot: u8,
+    /// seeds in the slot at the start, counted down for every plot seen planted
+    pub seeds: Option<usize>,
+}
+
+/// What to do when a seed slot runs out
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptySlotPolicy {
+    /// leave the plot empty
+    #[default]
+    Skip,
+    /// use the first generic replant slot with seeds left
+    Fallback,
+    Stop,
+}
+
+/// What to do after the selection is lost in the middle of an action
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecoveryChoice {
+    /// go back to waiting and try again
+    Resume,
+    /// move on to the next plot
+    Skip,
+    #[default]
+    Stop,
+}
+
+impl Default for CropPlan {
+    fn default() -> Self {
+        Self {
+            seed_slot: None,
+            fertilize: true,
+            extra_delay_secs: 0,
+        }
+    }
+}
+
+/// Named set of user settings, stored as `profiles/<name>.toml` next to the executable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub select_region: Option<WindowRegion>,
+    /// legacy areas in screen coordinates, converted once the game window is found
+    #[serde(skip_serializing)]
+    pub select_rect: Option<Rectangle<f64>>,
+    #[serde(skip_serializing)]
+    pub hotbar_rect: Option<Rectangle<f64>>,
+    #[serde(skip_serializing)]
+    pub sentinel_rect: Option<Rectangle<f64>>,
+    /// how newly selected areas follow the game window
+    pub region_anchor: Anchor,
+    pub num_rounds: usize,
+    /// goals other than the rounds, 0 or empty if not used
+    pub goal_plants: usize,
+    pub goal_minutes: usize,
+    /// "HH:MM"
+    pub stop_at: String,
+    pub until_out_of_stock: bool,
+    pub num_objects: usize,
+    pub extra_delay_secs: usize,
+    pub input_method: InputMethod,
+    /// how many times an action is repeated if the game doesn't seem to react
+    pub action_retries: usize,
+    pub per_plot: bool,
+    /// how often the selected entity is read while something is expected to change
+    pub min_capture_interval_ms: u64,
+    /// how often it's read while nothing is going on
+    pub max_capture_interval_ms: u64,
+    /// per crop name
+    pub crops: BTreeMap<String, CropPlan>,
+    pub replant_slots: Vec<ReplantSlot>,
+    /// how many times each replant slot is pressed when replanting all plots at once
+    pub replant_presses: usize,
+    pub empty_slot_policy: EmptySlotPolicy,
+    pub hotbar_region: Option<WindowRegion>,
+    /// slots whose stack counts are read from the hotbar
+    pub hotbar_slots: Vec<HotbarSlot>,
+    /// how long to wait for the user to decide after the selection was lost
+    pub recovery_timeout_secs: u64,
+    /// what happens when nobody decides in time
+    pub recovery_auto_choice: RecoveryChoice,
+    /// pause if the keyboard or mouse was used that recently, 0 to never pause
+    pub user_active_secs: u64,
+    /// resume once they weren't used for that long
+    pub user_idle_secs: u64,
+    /// safety limits that stop the session, 0 for no limit
+    pub max_keypresses_per_minute: usize,
+    /// screen reads in a row where nothing was recognized
+    pub max_unknown_reads: usize,
+    /// action rounds in a row that didn't change the state of the plot acted on
+    pub max_rounds_without_change: usize,
+    /// time spent watering, fertilizing, harvesting or replanting at once
+    pub max_action_state_secs: u64,
+    /// part of the game screen that never stays still, to tell if the game froze
+    pub sentinel_region: Option<WindowRegion>,
+    /// pause while the game window is gone, minimized, blank or frozen
+    pub display_check: bool,
+    /// the sentinel area didn't change for that long, 0 to never consider the game frozen
+    pub frozen_secs: u64,
+    /// title of the game window, `*` stands for any text
+    pub window_title: String,
+    /// class of the game window, empty for any
+    pub window_class: String,
+    /// executable name of the game, empty for any
+    pub window_process: String,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            select_region: None,
+            select_rect: None,
+            hotbar_rect: None,
+            sentinel_rect: None,
+            region_anchor: Anchor::default(),
+            num_rounds: 0,
+            goal_plants: 0,
+            goal_minutes: 0,
+            stop_at: String::new(),
+            until_out_of_stock: false,
+            num_objects: 0,
+            extra_delay_secs: 0,
+            input_method: InputMethod::default(),
+            action_retries: 2,
+            per_plot: false,
+            min_capture_interval_ms: 1500,
+            max_capture_interval_ms: 6000,
+            crops: BTreeMap::new(),
+            replant_slots: (1..=5)
+                .map(|slot| ReplantSlot { slot, seeds: None })
+                .collect(),
+            replant_presses: 3,
+            empty_slot_policy: EmptySlotPolicy::default(),
+            hotbar_region: None,
+            hotbar_slots: Vec::new(),
+            recovery_timeout_secs: 60,
+            recovery_auto_choice: RecoveryChoice::default(),
+            user_active_secs: 3,
+            user_idle_secs: 10,
+            max_keypresses_per_minute: 300,
+            max_unknown_reads: 40,
+            max_rounds_without_change: 10,
+            max_action_state_secs: 600,
+            sentinel_region: None,
+            display_check: true,
+            frozen_secs: 20,
+            window_title: "Project Gorgon".to_string(),
+            window_class: String::new(),
+            window_process: String::new(),
+        }
+    }
+}
+
+impl Profile {
+    fn dir() -> PathBuf {
+        let exe = std::env::current_exe().unwrap();
+        exe.parent().unwrap().join("profiles")
+    }
+
+    fn path(name: &str) -> PathBuf {
+        Self::dir().join(format!("{name}.toml"))
+    }
+
+    /// Names of all saved profiles, always including the default one
+    pub fn list() -> Vec<String> {
+        let mut names = vec![DEFAULT_PROFILE.to_string()];
+        if let Ok(entries) = std::fs::read_dir(Self::dir()) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "toml") {
+                    let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+                    if name != DEFAULT_PROFILE {
+                        names.push(name);
+                    }
+                }
+            }
+        }
+        names[1..].sort();
+        names
+    }
+
+    /// Load the profile, or fall back to defaults if it doesn't exist or can't be parsed
+    pub fn load(name: &str) -> Self {
+        let Ok(contents) = std::fs::read_to_string(Self::path(name)) else {
+            return Self::default();
+        };
+
+        toml::from_str(&contents).unwrap_or_else(|e| {
+            error!("Failed to parse profile {name}: {e}");
+            Self::default()
+        })
+    }
+
+    pub fn capture_schedule(&self) -> CaptureSchedule {
+        CaptureSchedule::new(
+            Duration::from_millis(self.min_capture_interval_ms),
+            Duration::from_millis(self.max_capture_interval_ms),
+        )
+    }
+
+    /// Game windows matching the profile, whichever client
+    pub fn window_query(&self) -> WindowQuery {
+        WindowQuery {
+            title: self.window_title.clone(),
+            class: self.window_class.clone(),
+            process: self.window_process.clone(),
+            pid: None,
+        }
+    }
+
+    pub fn save(&self, name: &str) -> std::io::Result<()> {
+        std::fs::create_dir_all(Self::dir())?;
+        let contents = toml::to_string_pretty(self).unwrap();
+        std::fs::write(Self::path(name), contents)
+    }
+}
@@ -1,10 +1,14 @@
 #[derive(Debug)]
 pub struct Screenshot {
-    pub bgra: Vec<u8>,
+    /// bottom-up BGR, as GDI gives it
+    pub image: Image,
+    /// captured part of the virtual screen, in its coordinates
     pub bounds: Rectangle<i32>,
+    /// the same part in the coordinates of a whole virtual screen screenshot
+    pub area: Rectangle<u32>,
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub struct Rectangle<T> {
     pub x: T,
     pub y: T,
@@ -12,9 +16,16 @@ pub struct Rectangle<T> {
     pub h: T,
 }
 
-use crate::bmp::{BMPHeader, InfoHeader};
+#[cfg(windows)]
+use crate::geometry;
+use crate::image::{Image, ImageView};
+#[cfg(windows)]
+use crate::image::{Orientation, PixelFormat};
 
-use std::{io::Write, mem::size_of, ptr::null_mut};
+use serde::{Deserialize, Serialize};
+#[cfg(windows)]
+use std::{mem::size_of, ptr::null_mut};
+#[cfg(windows)]
 use winapi::{
     ctypes::c_void,
     um::{
@@ -29,13 +40,36 @@ use winapi::{
     },
 };
 
+#[cfg(windows)]
 impl Screenshot {
+    /// Capture all monitors
     pub fn take() -> Self {
-        // get virtual screen bounds (covers all monitors)
-        let x = unsafe { GetSystemMetrics(SM_XVIRTUALSCREEN) };
-        let y = unsafe { GetSystemMetrics(SM_YVIRTUALSCREEN) };
-        let w = unsafe { GetSystemMetrics(SM_CXVIRTUALSCREEN) };
-        let h = unsafe { GetSystemMetrics(SM_CYVIRTUALSCREEN) };
+        Self::capture(full_area())
+    }
+
+    /// Capture just the given part of the screen, in the coordinates of a
+    /// whole screen screenshot. Much cheaper than [`Self::take`].
+    pub fn take_region(region: Rectangle<u32>) -> Self {
+        Self::take_region_with_margin(region, 0)
+    }
+
+    /// Same as [`Self::take_region`], plus up to `margin` pixels around
+    pub fn take_region_with_margin(region: Rectangle<u32>, margin: u32) -> Self {
+        let grown = Rectangle {
+            x: region.x as f64 - margin as f64,
+            y: region.y as f64 - margin as f64,
+            w: (region.w + 2 * margin) as f64,
+            h: (region.h + 2 * margin) as f64,
+        };
+        Self::capture(geometry::clip(grown, full_area()).unwrap_or(region))
+    }
+
+    fn capture(area: Rectangle<u32>) -> Self {
+        let screen = virtual_screen();
+        let (x, y) = (screen.x + area.x as i32, screen.y + area.y as i32);
+        let (w, h) = (area.w as i32, area.h as i32);
+        // GDI pads each row to 4 bytes
+        let stride = (area.w as usize * 3).next_multiple_of(4);
 
         let mut bi = BITMAPINFO {
             bmiHeader: BITMAPINFOHEADER {
@@ -63,7 +97,7 @@ impl Screenshot {
         let h_dc = unsafe { CreateCompatibleDC(h_screen) };
         let h_bitmap = unsafe { CreateCompatibleBitmap(h_screen, w, h) };
 
-        let mut bgra = Vec::with_capacity((w * h * 3) as usize);
+        let mut bgr = Vec::with_capacity(stride * h as usize);
 
         unsafe {
             let old_obj = SelectObject(h_dc, h_bitmap as *mut c_void);
@@ -75,12 +109,12 @@ impl Screenshot {
                 h_bitmap,
                 0,
                 h as u32,
-                bgra.as_mut_ptr() as *mut c_void,
+                bgr.as_mut_ptr() as *mut c_void,
                 &mut bi,
                 DIB_RGB_COLORS,
             );
 
-            bgra.set_len(bgra.capacity());
+            bgr.set_len(bgr.capacity());
             SelectObject(h_dc, old_obj);
         }
 
@@ -91,38 +125,52 @@ impl Screenshot {
         }
 
         Screenshot {
+            image: Image::from_raw(
+                area.w,
+                area.h,
+                stride,
+                PixelFormat::Bgr,
+                Orientation::BottomUp,
+                bgr,
+            )
+            .unwrap(),
             bounds: Rectangle { x, y, w, h },
-            bgra,
+            area,
         }
     }
+}
 
-    pub fn get_bmp_data(&self, region: Rectangle<u32>) -> Vec<u8> {
-        let mut file: Vec<u8> = Vec::new();
-
-        let row_size = (region.w * 3).next_multiple_of(4) as usize;
-        let row_padding_nbytes = row_size - (region.w * 3) as usize;
-
-        file.write_all(bytemuck::bytes_of(&BMPHeader::new(
-            row_size * region.h as usize,
-        )))
-        .unwrap();
-        file.write_all(bytemuck::bytes_of(&InfoHeader::new(
-            region.w,
-            region.h.wrapping_neg(),
-        )))
-        .unwrap();
-
-        for h in 0..region.h {
-            let row_start_byte = 3
-                * (((self.bounds.h - 1) as u32 - region.y - h) * self.bounds.w as u32 + region.x)
-                    as usize;
-            let row_end_byte = row_start_byte + 3 * (region.w as usize);
-            let row = &self.bgra[row_start_byte..row_end_byte];
-            file.write_all(row).unwrap();
-            let zeroes = [0u8; 3];
-            file.write_all(&zeroes[0..row_padding_nbytes]).unwrap();
-        }
+impl Screenshot {
+    /// Part of the screenshot, in the coordinates of a whole screen
+    /// screenshot. None if it wasn't captured.
+    pub fn view(&self, region: Rectangle<u32>) -> Option<ImageView<'_>> {
+        self.image.sub_image(Rectangle {
+            x: region.x.checked_sub(self.area.x)?,
+            y: region.y.checked_sub(self.area.y)?,
+            ..region
+        })
+    }
+}
+
+/// Whole virtual screen in the coordinates of its screenshot
+#[cfg(windows)]
+pub fn full_area() -> Rectangle<u32> {
+    let screen = virtual_screen();
+    Rectangle {
+        x: 0,
+        y: 0,
+        w: screen.w as u32,
+        h: screen.h as u32,
+    }
+}
 
-        file
+/// Bounds of the virtual screen, which covers all monitors
+#[cfg(windows)]
+pub fn virtual_screen() -> Rectangle<i32> {
+    Rectangle {
+        x: unsafe { GetSystemMetrics(SM_XVIRTUALSCREEN) },
+        y: unsafe { GetSystemMetrics(SM_YVIRTUALSCREEN) },
+        w: unsafe { GetSystemMetrics(SM_CXVIRTUALSCREEN) },
+        h: unsafe { GetSystemMetrics(SM_CYVIRTUALSCREEN) },
     }
 }
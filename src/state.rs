@@ -0,0 +1,288 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Instant;
+
+use crate::grower::CurrentlySelected;
+use crate::log::log;
+use crate::watchdog::WatchdogTrip;
+
+/// How many transitions are kept in the log
+const LOG_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrowerState {
+    /// no session was started yet
+    Idle,
+    /// session is running, but no crop needs anything right now
+    Waiting,
+    Watering,
+    Fertilizing,
+    Harvesting,
+    Replanting,
+    /// `from` is the state that was interrupted, it goes on once resumed
+    Paused {
+        from: Box<GrowerState>,
+    },
+    /// cycling through the plots to find out how many there are
+    Counting,
+    Stopped {
+        reason: StopReason,
+    },
+}
+
+impl GrowerState {
+    /// Whether a session is in progress, including a paused one
+    pub fn is_running(&self) -> bool {
+        !matches!(self, Self::Idle | Self::Stopped { .. })
+    }
+
+    pub fn is_paused(&self) -> bool {
+        matches!(self, Self::Paused { .. })
+    }
+}
+
+impl fmt::Display for GrowerState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Idle => f.write_str("Idle"),
+            Self::Waiting => f.write_str("Waiting"),
+            Self::Watering => f.write_str("Watering"),
+            Self::Fertilizing => f.write_str("Fertilizing"),
+            Self::Harvesting => f.write_str("Harvesting"),
+            Self::Replanting => f.write_str("Replanting"),
+            Self::Paused { .. } => f.write_str("Paused"),
+            Self::Counting => f.write_str("Counting plots"),
+            Self::Stopped { reason } => write!(f, "Stopped: {reason}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    UserRequest,
+    FinishedAllRounds,
+    WindowNotFound,
+    SelectionChanged,
+    /// the selected entity didn't change as expected after the action, even after retrying
+    ActionNotConfirmed(CurrentlySelected),
+    NoScreenReads,
+    NothingSelected,
+    /// the plots repeat after just one, so we can't tell them apart
+    PlotsLookAlike,
+    NoCycleFound,
+    /// the plots seem to repeat after both numbers
+    AmbiguousCycle(usize, usize),
+    /// the given hotbar slot, or all of them if None
+    OutOfSeeds(Option<u8>),
+    /// the watched hotbar stack with the given name
+    LowStock(String),
+    HarvestedAllPlants,
+    TimeIsUp,
+    /// the watched hotbar stack with the given name is empty
+    OutOfStock(String),
+    /// one of the safety limits was exceeded
+    Watchdog(WatchdogTrip),
+}
+
+impl StopReason {
+    /// Whether the user can fix it and resume the session
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            Self::WindowNotFound
+                | Self::SelectionChanged
+                | Self::ActionNotConfirmed(_)
+                | Self::NoScreenReads
+        )
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UserRequest => f.write_str("Requested by the user"),
+            Self::FinishedAllRounds => f.write_str("Finished all rounds"),
+            Self::WindowNotFound => f.write_str("Can't find Project Gorgon window"),
+            Self::SelectionChanged => f.write_str("Selection changed abruptly"),
+            Self::ActionNotConfirmed(sel) => {
+                let action = match sel {
+                    CurrentlySelected::Thisty => "watering",
+                    CurrentlySelected::Hungry => "fertilizing",
+                    CurrentlySelected::Ripe => "harvesting",
+                    CurrentlySelected::None | CurrentlySelected::Growing => "the action",
+                };
+                write!(f, "Game didn't react to {action}")
+            }
+            Self::NoScreenReads => f.write_str("Screen reader stopped responding"),
+            Self::NothingSelected => f.write_str("Nothing is selected in the game"),
+            Self::PlotsLookAlike => f.write_str("All plots look the same, count them by hand"),
+            Self::NoCycleFound => f.write_str("Couldn't find where the plots repeat"),
+            Self::AmbiguousCycle(a, b) => {
+                write!(
+                    f,
+                    "The plots repeat after either {a} or {b}, count them by hand"
+                )
+            }
+            Self::OutOfSeeds(Some(slot)) => write!(f, "Hotbar slot {slot} is out of seeds"),
+            Self::OutOfSeeds(None) => f.write_str("All seed slots are empty"),
+            Self::LowStock(name) => write!(f, "{name} is running out"),
+            Self::HarvestedAllPlants => f.write_str("Harvested all plants"),
+            Self::TimeIsUp => f.write_str("Time is up"),
+            Self::OutOfStock(name) => write!(f, "Ran out of {name}"),
+            Self::Watchdog(trip) => write!(f, "Safety limit exceeded: {trip}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrowerEvent {
+    Start,
+    Count,
+    /// the selected entity needs something
+    Selected(CurrentlySelected),
+    /// the keypresses of the current action were all sent
+    ActionDone,
+    Pause,
+    Resume,
+    /// resume, but leave the interrupted action and go on with the next plot
+    Skip,
+    Stop(StopReason),
+}
+
+#[derive(Debug, Clone)]
+pub struct Transition {
+    pub at: Instant,
+    pub from: GrowerState,
+    pub to: GrowerState,
+    pub reason: String,
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}: {}", self.from, self.to, self.reason)
+    }
+}
+
+#[derive(Debug)]
+pub struct StateMachine {
+    state: GrowerState,
+    log: VecDeque<Transition>,
+}
+
+impl StateMachine {
+    pub fn new() -> Self {
+        Self {
+            state: GrowerState::Idle,
+            log: VecDeque::with_capacity(LOG_CAPACITY),
+        }
+    }
+
+    pub fn state(&self) -> &GrowerState {
+        &self.state
+    }
+
+    pub fn last_transition(&self) -> Option<&Transition> {
+        self.log.back()
+    }
+
+    /// Apply the event. Returns false if it's not valid in the current state,
+    /// in which case nothing changes.
+    pub fn handle(&mut self, at: Instant, event: GrowerEvent, reason: &str) -> bool {
+        let Some(to) = next_state(&self.state, &event) else {
+            return false;
+        };
+
+        let from = std::mem::replace(&mut self.state, to.clone());
+        let transition = Transition {
+            at,
+            from,
+            to,
+            reason: reason.to_string(),
+        };
+        log!("{transition}");
+
+        if self.log.len() == LOG_CAPACITY {
+            self.log.pop_front();
+        }
+        self.log.push_back(transition);
+        true
+    }
+}
+
+fn next_state(state: &GrowerState, event: &GrowerEvent) -> Option<GrowerState> {
+    use CurrentlySelected as C;
+    use GrowerEvent as E;
+    use GrowerState as S;
+
+    let next = match (state, event) {
+        (S::Idle | S::Stopped { .. }, E::Start) => S::Waiting,
+        (S::Idle | S::Stopped { .. }, E::Count) => S::Counting,
+        (S::Idle | S::Stopped { .. }, _) => return None,
+
+        (S::Waiting, E::Selected(C::Thisty)) => S::Watering,
+        (S::Waiting, E::Selected(C::Hungry)) => S::Fertilizing,
+        (S::Waiting, E::Selected(C::Ripe)) => S::Harvesting,
+        (S::Watering | S::Fertilizing | S::Replanting, E::ActionDone) => S::Waiting,
+        (S::Harvesting, E::ActionDone) => S::Replanting,
+        (S::Counting, E::ActionDone) => S::Idle,
+        (S::Counting, E::Pause) => return None,
+
+        (S::Paused { from }, E::Resume) => (**from).clone(),
+        (S::Paused { .. }, E::Skip) => S::Waiting,
+        (S::Paused { .. }, E::Pause) => return None,
+        (_, E::Pause) => S::Paused {
+            from: Box::new(state.clone()),
+        },
+
+        (_, E::Stop(reason)) => S::Stopped {
+            reason: reason.clone(),
+        },
+        _ => return None,
+    };
+    Some(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn machine(state: GrowerState) -> StateMachine {
+        StateMachine {
+            state,
+            log: VecDeque::new(),
+        }
+    }
+
+    #[test]
+    fn resumes_what_the_pause_interrupted() {
+        let now = Instant::now();
+        for interrupted in [
+            GrowerState::Waiting,
+            GrowerState::Harvesting,
+            GrowerState::Replanting,
+        ] {
+            let mut machine = machine(interrupted.clone());
+            assert!(machine.handle(now, GrowerEvent::Pause, "pause"));
+            assert!(machine.state().is_paused());
+            assert!(!machine.handle(now, GrowerEvent::Pause, "pause again"));
+            assert!(machine.handle(now, GrowerEvent::Resume, "resume"));
+            assert_eq!(*machine.state(), interrupted);
+        }
+    }
+
+    #[test]
+    fn skip_leaves_the_interrupted_action() {
+        let now = Instant::now();
+        let mut machine = machine(GrowerState::Watering);
+        assert!(machine.handle(now, GrowerEvent::Pause, "pause"));
+        assert!(machine.handle(now, GrowerEvent::Skip, "skip"));
+        assert_eq!(*machine.state(), GrowerState::Waiting);
+    }
+
+    #[test]
+    fn counting_cant_be_paused() {
+        let mut machine = machine(GrowerState::Counting);
+        assert!(!machine.handle(Instant::now(), GrowerEvent::Pause, "pause"));
+        assert_eq!(*machine.state(), GrowerState::Counting);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::garden::same_crop;
+use crate::grower::CurrentlySelected;
+use crate::log::{error, log};
+
+/// How long it took a crop to get from growing to a specific state
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StageStats {
+    pub samples: u32,
+    pub avg_secs: f64,
+}
+
+impl StageStats {
+    fn add(&mut self, dur: Duration) {
+        self.samples += 1;
+        self.avg_secs += (dur.as_secs_f64() - self.avg_secs) / self.samples as f64;
+    }
+
+    pub fn average(&self) -> Option<Duration> {
+        if self.samples == 0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(self.avg_secs))
+    }
+}
+
+/// Where a crop went after one growth stage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NextStages {
+    pub thirsty: StageStats,
+    pub hungry: StageStats,
+    pub ripe: StageStats,
+}
+
+impl NextStages {
+    fn stage_mut(&mut self, to: CurrentlySelected) -> Option<&mut StageStats> {
+        use CurrentlySelected as C;
+        match to {
+            C::Thisty => Some(&mut self.thirsty),
+            C::Hungry => Some(&mut self.hungry),
+            C::Ripe => Some(&mut self.ripe),
+            C::None | C::Growing => None,
+        }
+    }
+
+    /// The soonest of the stages seen so far
+    fn earliest(&self) -> Option<Duration> {
+        [&self.thirsty, &self.hungry, &self.ripe]
+            .into_iter()
+            .filter_map(StageStats::average)
+            .min()
+    }
+}
+
+/// Growth times of a crop, split by what it needed before it started growing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CropStats {
+    pub after_planting: NextStages,
+    pub after_watering: NextStages,
+    pub after_fertilizing: NextStages,
+}
+
+impl CropStats {
+    fn after(&self, from: CurrentlySelected) -> Option<&NextStages> {
+        use CurrentlySelected as C;
+        match from {
+            C::None => Some(&self.after_planting),
+            C::Thisty => Some(&self.after_watering),
+            C::Hungry => Some(&self.after_fertilizing),
+            C::Growing | C::Ripe => None,
+        }
+    }
+
+    fn after_mut(&mut self, from: CurrentlySelected) -> Option<&mut NextStages> {
+        use CurrentlySelected as C;
+        match from {
+            C::None => Some(&mut self.after_planting),
+            C::Thisty => Some(&mut self.after_watering),
+            C::Hungry => Some(&mut self.after_fertilizing),
+            C::Growing | C::Ripe => None,
+        }
+    }
+
+    /// How long until the crop needs anything, counted from when it started
+    /// growing after being `from`
+    pub fn next_action(&self, from: CurrentlySelected) -> Option<Duration> {
+        self.after(from)?.earliest()
+    }
+}
+
+/// Growth times learned from the observed plots, per crop name. Stored as
+/// `growth_stats.toml` next to the executable.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GrowthStats {
+    crops: BTreeMap<String, CropStats>,
+    /// where they were loaded from, None to keep them in memory only
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl GrowthStats {
+    fn path() -> PathBuf {
+        let exe = std::env::current_exe().unwrap();
+        exe.parent().unwrap().join("growth_stats.toml")
+    }
+
+    /// Stats of all bots in this process, loaded on first use. They share
+    /// them so one doesn't overwrite the samples saved by the others.
+    pub fn shared() -> Arc<Mutex<Self>> {
+        static SHARED: OnceLock<Arc<Mutex<GrowthStats>>> = OnceLock::new();
+        SHARED
+            .get_or_init(|| Arc::new(Mutex::new(Self::load())))
+            .clone()
+    }
+
+    /// Load the stats, or start from scratch if there are none yet
+    fn load() -> Self {
+        let path = Self::path();
+        let stats = match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+                error!("Failed to parse growth stats: {e}");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+        Self {
+            path: Some(path),
+            ..stats
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = toml::to_string_pretty(self).unwrap();
+        std::fs::write(path, contents)
+    }
+
+    /// Name the crop is stored under, allowing for OCR mistakes
+    fn key(&self, crop: &str) -> Option<&str> {
+        if let Some((key, _)) = self.crops.get_key_value(crop) {
+            return Some(key);
+        }
+        self.crops
+            .keys()
+            .map(String::as_str)
+            .find(|key| same_crop(key, crop))
+    }
+
+    pub fn crop(&self, crop: &str) -> Option<&CropStats> {
+        self.crops.get(self.key(crop)?)
+    }
+
+    /// The crop started growing after being `from` and was seen going to `to`
+    /// after `dur`
+    pub fn record(
+        &mut self,
+        crop: &str,
+        from: CurrentlySelected,
+        to: CurrentlySelected,
+        dur: Duration,
+    ) {
+        let key = self.key(crop).unwrap_or(crop).to_string();
+        let stats = self.crops.entry(key).or_default();
+        if let Some(stage) = stats.after_mut(from).and_then(|next| next.stage_mut(to)) {
+            stage.add(dur);
+            log!("{crop} took {}s from {from:?} to {to:?}", dur.as_secs());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CurrentlySelected as C;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn keeps_each_transition_apart() {
+        let mut stats = GrowthStats::default();
+        stats.record("Cotton", C::None, C::Thisty, secs(60));
+        stats.record("Cotton", C::Thisty, C::Ripe, secs(600));
+        stats.record("Cotton", C::Thisty, C::Ripe, secs(400));
+
+        let cotton = stats.crop("Cotton").unwrap();
+        assert_eq!(cotton.next_action(C::None), Some(secs(60)));
+        assert_eq!(cotton.next_action(C::Thisty), Some(secs(500)));
+        assert_eq!(cotton.next_action(C::Hungry), None);
+    }
+
+    #[test]
+    fn takes_the_soonest_next_stage() {
+        let mut stats = GrowthStats::default();
+        stats.record("Flax", C::None, C::Ripe, secs(300));
+        stats.record("Flax", C::None, C::Hungry, secs(120));
+        assert_eq!(
+            stats.crop("Flax").unwrap().next_action(C::None),
+            Some(secs(120))
+        );
+    }
+
+    #[test]
+    fn all_bots_share_the_same_stats() {
+        assert!(Arc::ptr_eq(&GrowthStats::shared(), &GrowthStats::shared()));
+    }
+
+    #[test]
+    fn misread_crop_names_share_their_stats() {
+        let mut stats = GrowthStats::default();
+        stats.record("Cotton", C::None, C::Thisty, secs(60));
+        stats.record("Cottom", C::None, C::Thisty, secs(80));
+
+        assert_eq!(stats.crops.len(), 1);
+        let cotton = stats.crop("C0tton").unwrap();
+        assert_eq!(cotton.after_planting.thirsty.samples, 2);
+        assert_eq!(cotton.next_action(C::None), Some(secs(70)));
+        assert!(stats.crop("Barley").is_none());
+    }
+}
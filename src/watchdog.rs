@@ -0,0 +1,250 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::state::GrowerState;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// Limits that stop a session gone out of control, e.g. because the screen
+/// reader keeps reading garbage. None means no limit.
+#[derive(Debug, Clone, Default)]
+pub struct WatchdogLimits {
+    pub keypresses_per_minute: Option<usize>,
+    /// reads in a row where nothing was recognized
+    pub unknown_reads: Option<usize>,
+    /// action rounds in a row where the plot acted on didn't change its state
+    pub rounds_without_change: Option<usize>,
+    /// in watering, fertilizing, harvesting or replanting
+    pub action_state_time: Option<Duration>,
+}
+
+/// Which limit was exceeded
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchdogTrip {
+    Keypresses(usize),
+    UnknownReads(usize),
+    RoundsWithoutChange(usize),
+    /// name of the state and how long it took
+    StuckInState(String, Duration),
+}
+
+impl fmt::Display for WatchdogTrip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Keypresses(max) => write!(f, "more than {max} keypresses a minute"),
+            Self::UnknownReads(max) => write!(f, "{max} unrecognized screen reads in a row"),
+            Self::RoundsWithoutChange(max) => {
+                write!(f, "{max} action rounds without any change")
+            }
+            Self::StuckInState(state, took) => {
+                write!(f, "{state} for over {}", crate::format_duration(*took))
+            }
+        }
+    }
+}
+
+/// Keeps track of a running session and tells when it exceeds any of the limits
+#[derive(Debug)]
+pub struct Watchdog {
+    limits: WatchdogLimits,
+    /// in the last minute
+    presses: VecDeque<Instant>,
+    unknown_reads: usize,
+    rounds_without_change: usize,
+    /// the current action state and since when
+    action_state: Option<(GrowerState, Instant)>,
+}
+
+impl Watchdog {
+    pub fn new(limits: WatchdogLimits) -> Self {
+        Self {
+            limits,
+            presses: VecDeque::new(),
+            unknown_reads: 0,
+            rounds_without_change: 0,
+            action_state: None,
+        }
+    }
+
+    /// A key was just pressed
+    pub fn pressed(&mut self, now: Instant) -> Option<WatchdogTrip> {
+        self.presses.push_back(now);
+        while self
+            .presses
+            .front()
+            .is_some_and(|&at| now.saturating_duration_since(at) >= MINUTE)
+        {
+            self.presses.pop_front();
+        }
+        let max = self.limits.keypresses_per_minute?;
+        (self.presses.len() > max).then_some(WatchdogTrip::Keypresses(max))
+    }
+
+    /// The screen was read, and something was recognized or not
+    pub fn read(&mut self, recognized: bool) -> Option<WatchdogTrip> {
+        if recognized {
+            self.unknown_reads = 0;
+            return None;
+        }
+        self.unknown_reads += 1;
+        let max = self.limits.unknown_reads?;
+        (self.unknown_reads >= max).then_some(WatchdogTrip::UnknownReads(max))
+    }
+
+    /// The plot acted on changed its state as expected
+    pub fn state_changed(&mut self) {
+        self.rounds_without_change = 0;
+    }
+
+    /// All keys of an action round were pressed
+    pub fn round_done(&mut self) -> Option<WatchdogTrip> {
+        self.rounds_without_change += 1;
+        let max = self.limits.rounds_without_change?;
+        (self.rounds_without_change > max).then_some(WatchdogTrip::RoundsWithoutChange(max))
+    }
+
+    /// Check how long the grower stays in its current state
+    pub fn check_state(&mut self, now: Instant, state: &GrowerState) -> Option<WatchdogTrip> {
+        use GrowerState as S;
+        if !matches!(
+            state,
+            S::Watering | S::Fertilizing | S::Harvesting | S::Replanting
+        ) {
+            self.action_state = None;
+            return None;
+        }
+        let since = match &self.action_state {
+            Some((prev, since)) if prev == state => *since,
+            _ => {
+                self.action_state = Some((state.clone(), now));
+                now
+            }
+        };
+        let max = self.limits.action_state_time?;
+        (now.saturating_duration_since(since) > max)
+            .then(|| WatchdogTrip::StuckInState(state.to_string(), max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, FakeClock};
+    use crate::state::StopReason;
+
+    #[test]
+    fn counts_keypresses_in_the_last_minute() {
+        let clock = FakeClock::new();
+        let mut watchdog = Watchdog::new(WatchdogLimits {
+            keypresses_per_minute: Some(3),
+            ..WatchdogLimits::default()
+        });
+        for _ in 0..3 {
+            assert_eq!(watchdog.pressed(clock.now()), None);
+            clock.advance(Duration::from_secs(10));
+        }
+        assert_eq!(
+            watchdog.pressed(clock.now()),
+            Some(WatchdogTrip::Keypresses(3))
+        );
+
+        // the first presses fall out of the window
+        clock.advance(Duration::from_secs(40));
+        assert_eq!(watchdog.pressed(clock.now()), None);
+    }
+
+    #[test]
+    fn counts_unknown_reads_in_a_row() {
+        let mut watchdog = Watchdog::new(WatchdogLimits {
+            unknown_reads: Some(3),
+            ..WatchdogLimits::default()
+        });
+        assert_eq!(watchdog.read(false), None);
+        assert_eq!(watchdog.read(false), None);
+        assert_eq!(watchdog.read(true), None);
+        assert_eq!(watchdog.read(false), None);
+        assert_eq!(watchdog.read(false), None);
+        assert_eq!(watchdog.read(false), Some(WatchdogTrip::UnknownReads(3)));
+    }
+
+    #[test]
+    fn counts_rounds_until_a_state_change() {
+        let mut watchdog = Watchdog::new(WatchdogLimits {
+            rounds_without_change: Some(2),
+            ..WatchdogLimits::default()
+        });
+        assert_eq!(watchdog.round_done(), None);
+        assert_eq!(watchdog.round_done(), None);
+        watchdog.state_changed();
+        assert_eq!(watchdog.round_done(), None);
+        assert_eq!(watchdog.round_done(), None);
+        assert_eq!(
+            watchdog.round_done(),
+            Some(WatchdogTrip::RoundsWithoutChange(2))
+        );
+    }
+
+    #[test]
+    fn limits_the_time_in_one_action_state() {
+        let clock = FakeClock::new();
+        let mut watchdog = Watchdog::new(WatchdogLimits {
+            action_state_time: Some(Duration::from_secs(60)),
+            ..WatchdogLimits::default()
+        });
+        let watering = GrowerState::Watering;
+        assert_eq!(watchdog.check_state(clock.now(), &watering), None);
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(watchdog.check_state(clock.now(), &watering), None);
+
+        // another action state starts over
+        clock.advance(Duration::from_secs(1));
+        let harvesting = GrowerState::Harvesting;
+        assert_eq!(watchdog.check_state(clock.now(), &harvesting), None);
+        clock.advance(Duration::from_secs(61));
+        assert_eq!(
+            watchdog.check_state(clock.now(), &harvesting),
+            Some(WatchdogTrip::StuckInState(
+                harvesting.to_string(),
+                Duration::from_secs(60)
+            ))
+        );
+    }
+
+    #[test]
+    fn waiting_is_never_stuck() {
+        let clock = FakeClock::new();
+        let mut watchdog = Watchdog::new(WatchdogLimits {
+            action_state_time: Some(Duration::from_secs(60)),
+            ..WatchdogLimits::default()
+        });
+        let states = [
+            GrowerState::Waiting,
+            GrowerState::Idle,
+            GrowerState::Stopped {
+                reason: StopReason::UserRequest,
+            },
+        ];
+        for state in states {
+            assert_eq!(watchdog.check_state(clock.now(), &state), None);
+            clock.advance(Duration::from_secs(3600));
+            assert_eq!(watchdog.check_state(clock.now(), &state), None);
+        }
+    }
+
+    #[test]
+    fn no_limits_never_trip() {
+        let clock = FakeClock::new();
+        let mut watchdog = Watchdog::new(WatchdogLimits::default());
+        for _ in 0..1000 {
+            assert_eq!(watchdog.pressed(clock.now()), None);
+            assert_eq!(watchdog.read(false), None);
+            assert_eq!(watchdog.round_done(), None);
+            assert_eq!(
+                watchdog.check_state(clock.now(), &GrowerState::Watering),
+                None
+            );
+            clock.advance(Duration::from_secs(60));
+        }
+    }
+}
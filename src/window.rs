@@ -0,0 +1,278 @@
+use std::fmt;
+use std::sync::Arc;
+
+#[cfg(windows)]
+use winapi::shared::minwindef::{BOOL, DWORD, LPARAM, TRUE};
+#[cfg(windows)]
+pub use winapi::shared::windef::HWND;
+#[cfg(windows)]
+use winapi::shared::windef::{POINT, RECT};
+#[cfg(windows)]
+use winapi::um::handleapi::CloseHandle;
+#[cfg(windows)]
+use winapi::um::processthreadsapi::OpenProcess;
+#[cfg(windows)]
+use winapi::um::winbase::QueryFullProcessImageNameW;
+#[cfg(windows)]
+use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+#[cfg(windows)]
+use winapi::um::winuser::{
+    ClientToScreen, EnumWindows, GetClassNameW, GetClientRect, GetWindowTextW,
+    GetWindowThreadProcessId, IsIconic, IsWindow, IsWindowVisible,
+};
+
+use crate::clock::Clock;
+#[cfg(windows)]
+use crate::input::GameInput;
+use crate::input::{InputMethod, KeyInput};
+#[cfg(windows)]
+use crate::screenshot::Rectangle;
+
+/// Window handle. There are no windows to drive outside of Windows, but the
+/// logic around them still builds and is tested there.
+#[cfg(not(windows))]
+#[allow(clippy::upper_case_acronyms)]
+pub type HWND = *mut std::ffi::c_void;
+
+/// Game windows as the grower sees them, so it can be driven without a real
+/// game
+pub trait Desktop: Send + Sync + fmt::Debug {
+    /// Window of the game client matching the query, see [`WindowQuery::find`]
+    fn find(&self, query: &WindowQuery) -> Option<HWND>;
+    /// Whether the handle still points to an existing window
+    fn is_alive(&self, hwnd: HWND) -> bool;
+    fn is_minimized(&self, hwnd: HWND) -> bool;
+    /// Keyboard of the window
+    fn input(&self, hwnd: HWND, method: InputMethod, clock: Arc<dyn Clock>) -> Box<dyn KeyInput>;
+}
+
+/// The real windows of this system
+#[cfg(windows)]
+#[derive(Debug)]
+pub struct SystemDesktop;
+
+#[cfg(windows)]
+impl Desktop for SystemDesktop {
+    fn find(&self, query: &WindowQuery) -> Option<HWND> {
+        query.find().map(|window| window.hwnd)
+    }
+
+    fn is_alive(&self, hwnd: HWND) -> bool {
+        is_alive(hwnd)
+    }
+
+    fn is_minimized(&self, hwnd: HWND) -> bool {
+        unsafe { IsIconic(hwnd) != 0 }
+    }
+
+    fn input(&self, hwnd: HWND, method: InputMethod, clock: Arc<dyn Clock>) -> Box<dyn KeyInput> {
+        Box::new(GameInput::new(hwnd, method, clock))
+    }
+}
+
+/// How game windows are recognized. Empty class or process match anything.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowQuery {
+    /// case-insensitive, `*` stands for any text
+    pub title: String,
+    pub class: String,
+    /// executable name, e.g. "WindowsPlayer.exe"
+    pub process: String,
+    /// the client picked by the user, if there are several
+    pub pid: Option<u32>,
+}
+
+/// Top-level window of a game client
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameWindow {
+    pub hwnd: HWND,
+    pub pid: u32,
+    pub title: String,
+    pub process: String,
+}
+
+impl fmt::Display for GameWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} {})", self.title, self.process, self.pid)
+    }
+}
+
+#[cfg(windows)]
+impl WindowQuery {
+    /// All visible windows that match, in the z-order
+    pub fn find_all(&self) -> Vec<GameWindow> {
+        let mut hwnds: Vec<HWND> = Vec::new();
+        unsafe { EnumWindows(Some(collect_window), &mut hwnds as *mut _ as LPARAM) };
+
+        hwnds
+            .into_iter()
+            .filter(|&hwnd| unsafe { IsWindowVisible(hwnd) } != 0)
+            .filter_map(|hwnd| {
+                let title = window_text(hwnd);
+                if !matches_pattern(&self.title, &title) {
+                    return None;
+                }
+                if !self.class.is_empty() && !matches_pattern(&self.class, &class_name(hwnd)) {
+                    return None;
+                }
+
+                let mut pid: DWORD = 0;
+                unsafe { GetWindowThreadProcessId(hwnd, &mut pid) };
+                let process = process_name(pid).unwrap_or_default();
+                if !self.process.is_empty() && !matches_pattern(&self.process, &process) {
+                    return None;
+                }
+                Some(GameWindow {
+                    hwnd,
+                    pid,
+                    title,
+                    process,
+                })
+            })
+            .collect()
+    }
+
+    /// Window of the picked client, None once it's gone. Without a picked
+    /// client, any matching window as long as there's just one, so we never
+    /// send keys to the wrong character.
+    pub fn find(&self) -> Option<GameWindow> {
+        pick_window(self.find_all(), self.pid)
+    }
+}
+
+/// The window of the client with the pid. Without a pid, the only window
+/// there is.
+fn pick_window(mut windows: Vec<GameWindow>, pid: Option<u32>) -> Option<GameWindow> {
+    if let Some(pid) = pid {
+        let pos = windows.iter().position(|window| window.pid == pid)?;
+        return Some(windows.swap_remove(pos));
+    }
+    if windows.len() == 1 {
+        windows.pop()
+    } else {
+        None
+    }
+}
+
+/// Whether the handle still points to an existing window
+#[cfg(windows)]
+pub fn is_alive(hwnd: HWND) -> bool {
+    unsafe { IsWindow(hwnd) != 0 }
+}
+
+/// Client area of the window in the virtual screen coordinates
+#[cfg(windows)]
+pub fn client_rect(hwnd: HWND) -> Option<Rectangle<i32>> {
+    let mut rect: RECT = unsafe { std::mem::zeroed() };
+    if unsafe { GetClientRect(hwnd, &mut rect) } == 0 {
+        return None;
+    }
+    let mut origin = POINT { x: 0, y: 0 };
+    if unsafe { ClientToScreen(hwnd, &mut origin) } == 0 {
+        return None;
+    }
+    Some(Rectangle {
+        x: origin.x,
+        y: origin.y,
+        w: rect.right - rect.left,
+        h: rect.bottom - rect.top,
+    })
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn collect_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let hwnds = &mut *(lparam as *mut Vec<HWND>);
+    hwnds.push(hwnd);
+    TRUE
+}
+
+#[cfg(windows)]
+fn window_text(hwnd: HWND) -> String {
+    let mut buf = [0u16; 256];
+    let len = unsafe { GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as i32) };
+    String::from_utf16_lossy(&buf[..len.max(0) as usize])
+}
+
+#[cfg(windows)]
+fn class_name(hwnd: HWND) -> String {
+    let mut buf = [0u16; 256];
+    let len = unsafe { GetClassNameW(hwnd, buf.as_mut_ptr(), buf.len() as i32) };
+    String::from_utf16_lossy(&buf[..len.max(0) as usize])
+}
+
+/// File name of the process executable
+#[cfg(windows)]
+fn process_name(pid: u32) -> Option<String> {
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if process.is_null() {
+        return None;
+    }
+
+    let mut buf = [0u16; 1024];
+    let mut len = buf.len() as DWORD;
+    let ok = unsafe { QueryFullProcessImageNameW(process, 0, buf.as_mut_ptr(), &mut len) };
+    unsafe { CloseHandle(process) };
+    if ok == 0 {
+        return None;
+    }
+
+    let path = String::from_utf16_lossy(&buf[..len as usize]);
+    path.rsplit('\\').next().map(str::to_string)
+}
+
+/// Case-insensitive match where `*` stands for any text
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let text = text.to_lowercase();
+
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = text.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        let Some(pos) = rest.find(part) else {
+            return false;
+        };
+        rest = &rest[pos + part.len()..];
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(pid: u32) -> GameWindow {
+        GameWindow {
+            hwnd: pid as usize as HWND,
+            pid,
+            title: "Project Gorgon".to_string(),
+            process: "WindowsPlayer.exe".to_string(),
+        }
+    }
+
+    #[test]
+    fn picks_the_client_with_the_pid() {
+        let picked = pick_window(vec![client(1), client(2)], Some(2));
+        assert_eq!(picked, Some(client(2)));
+    }
+
+    #[test]
+    fn takes_the_only_client_without_a_pid() {
+        assert_eq!(pick_window(vec![client(1)], None), Some(client(1)));
+        assert_eq!(pick_window(Vec::new(), None), None);
+    }
+
+    #[test]
+    fn does_not_guess_between_clients() {
+        assert_eq!(pick_window(vec![client(1), client(2)], None), None);
+        // the picked client is gone
+        assert_eq!(pick_window(vec![client(1), client(2)], Some(3)), None);
+        assert_eq!(pick_window(vec![client(1)], Some(3)), None);
+        assert_eq!(pick_window(Vec::new(), Some(3)), None);
+    }
+}